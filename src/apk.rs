@@ -0,0 +1,253 @@
+#[cfg(test)]
+mod test;
+
+use anyhow::{Context, Result, bail};
+use std::{fs::File, io::Read, path::Path};
+
+const RES_STRING_POOL_TYPE: u16 = 0x0001;
+const RES_XML_TYPE: u16 = 0x0003;
+const RES_XML_START_ELEMENT_TYPE: u16 = 0x0102;
+const RES_XML_RESOURCE_MAP_TYPE: u16 = 0x0180;
+const UTF8_FLAG: u32 = 1 << 8;
+const NO_INDEX: u32 = 0xFFFF_FFFF;
+const TYPE_STRING: u8 = 0x03;
+const ANDROID_NAME_ATTR: u32 = 0x0101_0003;
+
+/// The parts of a compiled `AndroidManifest.xml` needed to allowlist
+/// privileged permissions.
+#[derive(Debug, PartialEq)]
+pub struct ApkManifest {
+    pub package: String,
+    pub permissions: Vec<String>,
+}
+
+impl ApkManifest {
+    pub fn read(apk_path: &Path) -> Result<Self> {
+        let file = File::open(apk_path)
+            .with_context(|| format!("Failed open apk: '{}'", apk_path.display()))?;
+        let mut archive = zip::ZipArchive::new(file)
+            .with_context(|| format!("Invalid apk: '{}'", apk_path.display()))?;
+        let mut entry = archive
+            .by_name("AndroidManifest.xml")
+            .with_context(|| format!("Invalid apk: no manifest in '{}'", apk_path.display()))?;
+        let mut data: Vec<u8> = vec![];
+        entry.read_to_end(&mut data)?;
+        Self::parse(&data).with_context(|| format!("Invalid apk: '{}'", apk_path.display()))
+    }
+
+    /// Renders `privapp-permissions-<package>.xml`. Without an `allowlist`
+    /// every requested permission is granted, otherwise only the listed ones,
+    /// which must all be requested by the apk.
+    pub fn privapp_permissions(&self, allowlist: Option<&[String]>) -> Result<String> {
+        let permissions = match allowlist {
+            Some(value) => {
+                if let Some(permission) = value.iter().find(|p| !self.permissions.contains(p)) {
+                    bail!(
+                        "Invalid permission: '{}' is not requested by '{}'",
+                        permission,
+                        self.package
+                    );
+                }
+                value
+            }
+            None => &self.permissions,
+        };
+
+        let mut result =
+            String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<permissions>\n");
+        result.push_str(&format!(
+            "    <privapp-permissions package=\"{}\">\n",
+            escape_xml(&self.package)
+        ));
+        for permission in permissions {
+            result.push_str(&format!(
+                "        <permission name=\"{}\"/>\n",
+                escape_xml(permission)
+            ));
+        }
+        result.push_str("    </privapp-permissions>\n</permissions>\n");
+        Ok(result)
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if read_u16(data, 0)? != RES_XML_TYPE {
+            bail!("Invalid manifest: not a binary xml");
+        }
+
+        let mut strings: Vec<String> = vec![];
+        let mut resource_ids: Vec<u32> = vec![];
+        let mut package: Option<String> = None;
+        let mut permissions: Vec<String> = vec![];
+
+        let mut offset = usize::from(read_u16(data, 2)?);
+        while offset < data.len() {
+            let chunk_type = read_u16(data, offset)?;
+            let chunk_size = read_u32(data, offset + 4)? as usize;
+            if chunk_size < 8 || offset + chunk_size > data.len() {
+                bail!("Invalid manifest: broken chunk at {}", offset);
+            }
+            let chunk = &data[offset..offset + chunk_size];
+
+            match chunk_type {
+                RES_STRING_POOL_TYPE => strings = read_string_pool(chunk)?,
+                RES_XML_RESOURCE_MAP_TYPE => {
+                    let header_size = usize::from(read_u16(chunk, 2)?);
+                    resource_ids = (header_size..chunk_size)
+                        .step_by(4)
+                        .map(|pos| read_u32(chunk, pos))
+                        .collect::<Result<_>>()?;
+                }
+                RES_XML_START_ELEMENT_TYPE => {
+                    let element = Element::parse(chunk, &strings, &resource_ids)?;
+                    match element.name.as_str() {
+                        "manifest" => package = element.attribute("package"),
+                        "uses-permission" | "uses-permission-sdk-23" => {
+                            if let Some(value) = element.attribute("name")
+                                && !permissions.contains(&value)
+                            {
+                                permissions.push(value);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+            offset += chunk_size;
+        }
+
+        let package = package.context("Invalid manifest: no package name")?;
+        Ok(Self {
+            package,
+            permissions,
+        })
+    }
+}
+
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+}
+
+impl Element {
+    fn parse(chunk: &[u8], strings: &[String], resource_ids: &[u32]) -> Result<Self> {
+        let ext = usize::from(read_u16(chunk, 2)?);
+        let name = get_string(strings, read_u32(chunk, ext + 4)?)?;
+        let attribute_start = usize::from(read_u16(chunk, ext + 8)?);
+        let attribute_size = usize::from(read_u16(chunk, ext + 10)?);
+        let attribute_count = usize::from(read_u16(chunk, ext + 12)?);
+
+        let mut attributes: Vec<(String, String)> = vec![];
+        for index in 0..attribute_count {
+            let pos = ext + attribute_start + index * attribute_size;
+            let name_index = read_u32(chunk, pos + 4)?;
+            let raw_value = read_u32(chunk, pos + 8)?;
+            let data_type = *chunk.get(pos + 15).context("Invalid manifest: truncated")?;
+            let data = read_u32(chunk, pos + 16)?;
+
+            // aapt2 may strip attribute names, the resource id still tells
+            // `android:name` apart.
+            let mut attr_name = get_string(strings, name_index)?;
+            if resource_ids.get(name_index as usize) == Some(&ANDROID_NAME_ATTR) {
+                attr_name = "name".to_string();
+            }
+            let value = if raw_value != NO_INDEX {
+                get_string(strings, raw_value)?
+            } else if data_type == TYPE_STRING {
+                get_string(strings, data)?
+            } else {
+                continue;
+            };
+            attributes.push((attr_name, value));
+        }
+        Ok(Self { name, attributes })
+    }
+
+    fn attribute(&self, name: &str) -> Option<String> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.clone())
+    }
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn read_string_pool(chunk: &[u8]) -> Result<Vec<String>> {
+    let string_count = read_u32(chunk, 8)? as usize;
+    let flags = read_u32(chunk, 16)?;
+    let strings_start = read_u32(chunk, 20)? as usize;
+    let header_size = usize::from(read_u16(chunk, 2)?);
+
+    // The count comes from the APK; each string needs a 4-byte offset, so
+    // the chunk bounds the allocation.
+    let mut result: Vec<String> = Vec::with_capacity(string_count.min(chunk.len() / 4));
+    for index in 0..string_count {
+        let pos = strings_start + read_u32(chunk, header_size + index * 4)? as usize;
+        let value = if flags & UTF8_FLAG != 0 {
+            read_utf8(chunk, pos)?
+        } else {
+            read_utf16(chunk, pos)?
+        };
+        result.push(value);
+    }
+    Ok(result)
+}
+
+fn read_utf8(chunk: &[u8], pos: usize) -> Result<String> {
+    let (_, pos) = read_utf8_len(chunk, pos)?;
+    let (len, pos) = read_utf8_len(chunk, pos)?;
+    let bytes = chunk
+        .get(pos..pos + len)
+        .context("Invalid manifest: truncated string")?;
+    Ok(String::from_utf8_lossy(bytes).into_owned())
+}
+
+fn read_utf8_len(chunk: &[u8], pos: usize) -> Result<(usize, usize)> {
+    let first = usize::from(*chunk.get(pos).context("Invalid manifest: truncated")?);
+    if first & 0x80 == 0 {
+        return Ok((first, pos + 1));
+    }
+    let second = usize::from(*chunk.get(pos + 1).context("Invalid manifest: truncated")?);
+    Ok((((first & 0x7F) << 8) | second, pos + 2))
+}
+
+fn read_utf16(chunk: &[u8], pos: usize) -> Result<String> {
+    let mut len = usize::from(read_u16(chunk, pos)?);
+    let mut pos = pos + 2;
+    if len & 0x8000 != 0 {
+        len = ((len & 0x7FFF) << 16) | usize::from(read_u16(chunk, pos)?);
+        pos += 2;
+    }
+    let units = (0..len)
+        .map(|index| read_u16(chunk, pos + index * 2))
+        .collect::<Result<Vec<u16>>>()?;
+    Ok(String::from_utf16_lossy(&units))
+}
+
+fn get_string(strings: &[String], index: u32) -> Result<String> {
+    strings
+        .get(index as usize)
+        .cloned()
+        .with_context(|| format!("Invalid manifest: no string {}", index))
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Result<u16> {
+    let bytes = data
+        .get(pos..pos + 2)
+        .context("Invalid manifest: truncated")?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Result<u32> {
+    let bytes = data
+        .get(pos..pos + 4)
+        .context("Invalid manifest: truncated")?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
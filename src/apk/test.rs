@@ -0,0 +1,176 @@
+use super::*;
+use rstest::rstest;
+use std::{fs, io::Write};
+
+#[rstest]
+fn apk_manifest_parse(#[values(false, true)] utf8: bool) {
+    let data = manifest_xml(utf8, &["package", "name"], &[]);
+    let manifest = ApkManifest::parse(&data).unwrap();
+    assert_eq!(
+        manifest,
+        ApkManifest {
+            package: "com.example.companion".to_string(),
+            permissions: vec![
+                "android.permission.INSTALL_PACKAGES".to_string(),
+                "android.permission.INTERNET".to_string(),
+            ],
+        }
+    );
+}
+
+#[rstest]
+fn apk_manifest_parse_stripped_attribute_names() {
+    let data = manifest_xml(false, &["package", ""], &[0, ANDROID_NAME_ATTR]);
+    let manifest = ApkManifest::parse(&data).unwrap();
+    assert_eq!(manifest.permissions.len(), 2);
+}
+
+#[rstest]
+fn apk_manifest_parse_err(
+    #[values(vec![], vec![0x03, 0x00, 0x08, 0x00], vec![0x00; 16])] data: Vec<u8>,
+) {
+    assert!(ApkManifest::parse(&data).is_err());
+}
+
+#[rstest]
+fn read_string_pool_oversized_count() {
+    let mut data = string_pool(&["package"], true);
+    data[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert!(read_string_pool(&data).is_err());
+}
+
+#[rstest]
+fn apk_manifest_read() {
+    let dir = tempfile::tempdir().unwrap();
+    let apk_path = dir.path().join("Companion.apk");
+    let mut writer = zip::ZipWriter::new(fs::File::create(&apk_path).unwrap());
+    let options =
+        zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    writer.start_file("AndroidManifest.xml", options).unwrap();
+    writer
+        .write_all(&manifest_xml(false, &["package", "name"], &[]))
+        .unwrap();
+    writer.finish().unwrap();
+
+    let manifest = ApkManifest::read(&apk_path).unwrap();
+    assert_eq!(manifest.package, "com.example.companion");
+}
+
+#[rstest]
+fn apk_manifest_privapp_permissions() {
+    let manifest = ApkManifest {
+        package: "com.example.companion".to_string(),
+        permissions: vec![
+            "android.permission.INSTALL_PACKAGES".to_string(),
+            "android.permission.INTERNET".to_string(),
+        ],
+    };
+    let allowlist = vec!["android.permission.INSTALL_PACKAGES".to_string()];
+    let content = manifest.privapp_permissions(Some(&allowlist)).unwrap();
+    assert_eq!(
+        content,
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <permissions>\n    \
+             <privapp-permissions package=\"com.example.companion\">\n        \
+                 <permission name=\"android.permission.INSTALL_PACKAGES\"/>\n    \
+             </privapp-permissions>\n\
+         </permissions>\n"
+    );
+
+    let content = manifest.privapp_permissions(None).unwrap();
+    assert!(content.contains("android.permission.INTERNET"));
+
+    let allowlist = vec!["android.permission.REBOOT".to_string()];
+    assert!(manifest.privapp_permissions(Some(&allowlist)).is_err());
+}
+
+/// Builds a compiled manifest with a `manifest` element and two
+/// `uses-permission` elements. `attr_names` holds the pool strings used for
+/// the `package` and `name` attributes.
+fn manifest_xml(utf8: bool, attr_names: &[&str; 2], resource_ids: &[u32]) -> Vec<u8> {
+    let strings = [
+        attr_names[0],
+        attr_names[1],
+        "manifest",
+        "uses-permission",
+        "com.example.companion",
+        "android.permission.INSTALL_PACKAGES",
+        "android.permission.INTERNET",
+    ];
+    let mut body = string_pool(&strings, utf8);
+    if !resource_ids.is_empty() {
+        let ids: Vec<u8> = resource_ids
+            .iter()
+            .flat_map(|id| id.to_le_bytes())
+            .collect();
+        body.extend(chunk(RES_XML_RESOURCE_MAP_TYPE, 8, &ids));
+    }
+    body.extend(start_element(2, &[(0, 4)]));
+    body.extend(start_element(3, &[(1, 5)]));
+    body.extend(start_element(3, &[(1, 6)]));
+    body.extend(start_element(3, &[(1, 6)]));
+    chunk(RES_XML_TYPE, 8, &body)
+}
+
+fn string_pool(strings: &[&str], utf8: bool) -> Vec<u8> {
+    let mut data: Vec<u8> = vec![];
+    let mut offsets: Vec<u8> = vec![];
+    for value in strings {
+        offsets.extend((data.len() as u32).to_le_bytes());
+        if utf8 {
+            data.push(value.chars().count() as u8);
+            data.push(value.len() as u8);
+            data.extend(value.as_bytes());
+            data.push(0);
+        } else {
+            let units: Vec<u16> = value.encode_utf16().collect();
+            data.extend((units.len() as u16).to_le_bytes());
+            data.extend(units.iter().flat_map(|unit| unit.to_le_bytes()));
+            data.extend([0, 0]);
+        }
+    }
+    while !data.len().is_multiple_of(4) {
+        data.push(0);
+    }
+
+    let flags = if utf8 { UTF8_FLAG } else { 0 };
+    let mut header: Vec<u8> = vec![];
+    header.extend((strings.len() as u32).to_le_bytes());
+    header.extend(0u32.to_le_bytes());
+    header.extend(flags.to_le_bytes());
+    header.extend((28 + offsets.len() as u32).to_le_bytes());
+    header.extend(0u32.to_le_bytes());
+    header.extend(offsets);
+    header.extend(data);
+    chunk(RES_STRING_POOL_TYPE, 28, &header)
+}
+
+fn start_element(name: u32, attributes: &[(u32, u32)]) -> Vec<u8> {
+    let mut data: Vec<u8> = vec![];
+    data.extend(0u32.to_le_bytes());
+    data.extend(NO_INDEX.to_le_bytes());
+    data.extend(NO_INDEX.to_le_bytes());
+    data.extend(name.to_le_bytes());
+    data.extend(20u16.to_le_bytes());
+    data.extend(20u16.to_le_bytes());
+    data.extend((attributes.len() as u16).to_le_bytes());
+    data.extend([0; 6]);
+    for (attr_name, value) in attributes {
+        data.extend(NO_INDEX.to_le_bytes());
+        data.extend(attr_name.to_le_bytes());
+        data.extend(value.to_le_bytes());
+        data.extend(8u16.to_le_bytes());
+        data.extend([0, TYPE_STRING]);
+        data.extend(value.to_le_bytes());
+    }
+    chunk(RES_XML_START_ELEMENT_TYPE, 16, &data)
+}
+
+fn chunk(chunk_type: u16, header_size: u16, body: &[u8]) -> Vec<u8> {
+    let mut result: Vec<u8> = vec![];
+    result.extend(chunk_type.to_le_bytes());
+    result.extend(header_size.to_le_bytes());
+    result.extend((8 + body.len() as u32).to_le_bytes());
+    result.extend(body);
+    result
+}
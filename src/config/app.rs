@@ -0,0 +1,61 @@
+#[cfg(test)]
+mod test;
+
+use super::{Asset, ModuleProp};
+use crate::project::ProjectProvider;
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+use std::{path::PathBuf, rc::Rc};
+
+#[derive(Debug)]
+pub struct App {
+    pub apk: PathBuf,
+    pub name: String,
+    pub privileged: bool,
+    /// Privileged permissions to allowlist. `None` allowlists every
+    /// permission the apk requests.
+    pub permissions: Option<Vec<String>>,
+}
+
+impl App {
+    pub fn try_new(
+        apk: String,
+        name: Option<String>,
+        privileged: bool,
+        permissions: Option<Vec<String>>,
+        provider: &Rc<dyn ProjectProvider>,
+        module_prop: &ModuleProp,
+    ) -> Result<Self> {
+        let apk =
+            Asset::parse_source(apk, provider, module_prop).context("Failed to initialize App")?;
+        let name = match name {
+            Some(value) => value,
+            None => apk
+                .file_stem()
+                .map(|value| value.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        };
+
+        let re_name = Regex::new(r"^[a-zA-Z0-9][a-zA-Z0-9._-]*$")?;
+        if !re_name.is_match(&name) {
+            bail!("Invalid app name: '{}'", name);
+        }
+
+        if permissions.is_some() && !privileged {
+            bail!("Invalid app '{}': permissions require priv = true", name);
+        }
+
+        Ok(Self {
+            apk,
+            name,
+            privileged,
+            permissions,
+        })
+    }
+
+    /// Module relative directory the apk is installed into.
+    pub fn dir(&self) -> PathBuf {
+        let apps_dir = if self.privileged { "priv-app" } else { "app" };
+        PathBuf::from("system").join(apps_dir).join(&self.name)
+    }
+}
@@ -0,0 +1,72 @@
+use super::*;
+use crate::project::test_support::MockProject;
+use rstest::{fixture, rstest};
+
+#[rstest]
+#[case("target/Companion.apk", None, true, "system/priv-app/Companion")]
+#[case(
+    "app/build/app-release.apk",
+    Some("Helper"),
+    false,
+    "system/app/Helper"
+)]
+fn app_try_new(
+    #[case] apk: &str,
+    #[case] name: Option<&str>,
+    #[case] privileged: bool,
+    #[case] expected_dir: &str,
+    project_provider: Rc<dyn ProjectProvider>,
+    module_prop: ModuleProp,
+) {
+    let app = App::try_new(
+        apk.to_string(),
+        name.map(str::to_string),
+        privileged,
+        None,
+        &project_provider,
+        &module_prop,
+    )
+    .unwrap();
+    assert_eq!(app.dir(), PathBuf::from(expected_dir));
+}
+
+#[rstest]
+fn app_try_new_err(
+    #[values(
+        ("", None, true, None),
+        ("../Companion.apk", None, true, None),
+        ("Companion.apk", Some("My App"), true, None),
+        ("Companion.apk", None, false, Some(vec![])),
+    )]
+    input: (&str, Option<&str>, bool, Option<Vec<String>>),
+    project_provider: Rc<dyn ProjectProvider>,
+    module_prop: ModuleProp,
+) {
+    let (apk, name, privileged, permissions) = input;
+    let result = App::try_new(
+        apk.to_string(),
+        name.map(str::to_string),
+        privileged,
+        permissions,
+        &project_provider,
+        &module_prop,
+    );
+    assert!(result.is_err(), "result.is_err() not true. apk: '{}'", apk);
+}
+
+#[fixture]
+fn module_prop() -> ModuleProp {
+    ModuleProp::new(
+        "a_module".to_string(),
+        "name".to_string(),
+        "1.0.0".to_string(),
+        "author".to_string(),
+        None,
+    )
+    .unwrap()
+}
+
+#[fixture]
+fn project_provider() -> Rc<dyn ProjectProvider> {
+    Rc::new(MockProject::default())
+}
@@ -0,0 +1,50 @@
+use super::{Asset, ModuleProp};
+use crate::project::ProjectProvider;
+use anyhow::{Context, Result, bail};
+use std::{path::PathBuf, rc::Rc};
+
+/// Command producing an asset after the crate is built.
+#[derive(Debug, PartialEq)]
+pub struct Generator {
+    pub command: Vec<String>,
+    /// File or directory the command writes, passed to it as `OUT`.
+    pub output: PathBuf,
+    /// Files the output is made from. When set, the command is skipped
+    /// while none of them changed since its last successful run.
+    pub inputs: Option<Vec<PathBuf>>,
+}
+
+impl Generator {
+    pub fn try_new(
+        command: Vec<String>,
+        output: String,
+        inputs: Option<Vec<String>>,
+        provider: &Rc<dyn ProjectProvider>,
+        module_prop: &ModuleProp,
+    ) -> Result<Self> {
+        if command.first().is_none_or(|value| value.is_empty()) {
+            bail!("Invalid generate command: value is empty");
+        }
+
+        let output = Asset::parse_source(output, provider, module_prop)
+            .context("Invalid generate output")?;
+        let inputs = match inputs {
+            Some(values) => {
+                let mut result: Vec<PathBuf> = vec![];
+                for value in values {
+                    result.push(
+                        Asset::parse_source(value, provider, module_prop)
+                            .context("Invalid generate input")?,
+                    );
+                }
+                Some(result)
+            }
+            None => None,
+        };
+        Ok(Self {
+            command,
+            output,
+            inputs,
+        })
+    }
+}
@@ -0,0 +1,67 @@
+use anyhow::{Result, bail};
+
+/// Points of the deploy where user commands run.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum HookStage {
+    /// Before cargo builds the crate.
+    PreBuild,
+    /// After the build, before generated assets are produced.
+    PostBuild,
+    /// Before files are staged into the module dir.
+    PrePackage,
+    /// After the module dir is complete.
+    PostPackage,
+}
+
+impl HookStage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookStage::PreBuild => "pre_build",
+            HookStage::PostBuild => "post_build",
+            HookStage::PrePackage => "pre_package",
+            HookStage::PostPackage => "post_package",
+        }
+    }
+}
+
+/// Commands from `[package.metadata.magisk.hooks]`. A command given as a
+/// string runs through `sh -c`.
+#[derive(Debug, Default)]
+pub struct Hooks {
+    pub pre_build: Vec<Vec<String>>,
+    pub post_build: Vec<Vec<String>>,
+    pub pre_package: Vec<Vec<String>>,
+    pub post_package: Vec<Vec<String>>,
+}
+
+impl Hooks {
+    pub fn get(&self, stage: HookStage) -> &[Vec<String>] {
+        match stage {
+            HookStage::PreBuild => &self.pre_build,
+            HookStage::PostBuild => &self.post_build,
+            HookStage::PrePackage => &self.pre_package,
+            HookStage::PostPackage => &self.post_package,
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        for stage in [
+            HookStage::PreBuild,
+            HookStage::PostBuild,
+            HookStage::PrePackage,
+            HookStage::PostPackage,
+        ] {
+            for command in self.get(stage) {
+                if command.first().is_none_or(|value| value.is_empty()) {
+                    bail!("Invalid hooks.{}: command is empty", stage.name());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Converts a shell string into a `sh -c` command.
+pub fn shell_command(script: String) -> Vec<String> {
+    vec!["sh".to_string(), "-c".to_string(), script]
+}
@@ -0,0 +1,116 @@
+#[cfg(test)]
+mod test;
+
+use super::{Asset, check_id};
+use crate::project::{Manager, Target};
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+use std::path::Path;
+
+#[derive(Debug)]
+pub struct InstallOption {
+    pub name: String,
+    pub prompt: String,
+    pub default: bool,
+    pub timeout: u32,
+}
+
+impl InstallOption {
+    pub const DIR: &str = "options";
+
+    pub fn try_new(name: String, prompt: String, default: bool, timeout: u32) -> Result<Self> {
+        let re_name = Regex::new(r"^[a-zA-Z0-9_-]+$")?;
+        if !re_name.is_match(&name) {
+            bail!("Invalid option name: '{}'", name);
+        }
+
+        if prompt.is_empty() {
+            bail!("Invalid option prompt: value is empty");
+        }
+
+        if timeout == 0 {
+            bail!("Invalid option timeout: value must be at least 1 second");
+        }
+
+        Ok(Self {
+            name,
+            prompt,
+            default,
+            timeout,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Requires {
+    pub min_api: Option<u32>,
+    pub min_magisk: Option<u32>,
+    pub min_ksu: Option<u32>,
+    /// ABIs the module can be installed on, matched against the device
+    /// `ro.product.cpu.abilist`. Defaults to the built target.
+    pub abis: Vec<String>,
+    pub modules: Vec<String>,
+    pub conflicts: Vec<String>,
+}
+
+impl Requires {
+    pub fn new(target: &Target) -> Self {
+        Self {
+            min_api: None,
+            min_magisk: None,
+            min_ksu: None,
+            abis: vec![target.abi().to_string()],
+            modules: vec![],
+            conflicts: vec![],
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.abis.is_empty() {
+            bail!("Invalid abis: value is empty");
+        }
+
+        for abi in &self.abis {
+            if !Target::ABIS.contains(&abi.as_str()) {
+                bail!("Invalid abi: '{}'", abi);
+            }
+        }
+
+        for id in self.modules.iter().chain(&self.conflicts) {
+            check_id(id).with_context(|| format!("Invalid module: '{}'", id))?;
+        }
+
+        if let Some(id) = self.modules.iter().find(|id| self.conflicts.contains(id)) {
+            bail!("Invalid module: '{}' is both required and conflicting", id);
+        }
+        Ok(())
+    }
+}
+
+/// Turns a module relative path like `system/app/Foo` into the device path
+/// `/system/app/Foo` used by the installer `REPLACE` and `REMOVE` lists.
+/// Partitions are mapped like asset dests, so with Magisk `vendor/app/Foo`
+/// becomes `/system/vendor/app/Foo`.
+pub fn parse_device_path(path: &str, label: &str, manager: &Manager) -> Result<String> {
+    if path.is_empty() {
+        bail!("Invalid {}: value is empty", label);
+    }
+
+    Asset::check_path(Path::new(path), label)?;
+    if path.chars().any(char::is_whitespace) {
+        bail!("Invalid {}: contains whitespace", label);
+    }
+    let root = path.split('/').next().unwrap_or_default();
+    if !PARTITIONS.contains(&root) {
+        bail!(
+            "Invalid {}: '/{}' is not a partition a module can overlay",
+            label,
+            root
+        );
+    }
+    let path = Asset::map_partition(Path::new(path.trim_end_matches('/')), manager)
+        .with_context(|| format!("Invalid {}", label))?;
+    Ok(format!("/{}", path.display()))
+}
+
+const PARTITIONS: [&str; 6] = ["system", "vendor", "product", "system_ext", "odm", "oem"];
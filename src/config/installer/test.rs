@@ -0,0 +1,85 @@
+use super::*;
+use rstest::rstest;
+
+#[rstest]
+fn install_option_try_new(#[values("extra", "alt_config", "x86-shim")] name: &str) {
+    let result = InstallOption::try_new(name.to_string(), "Install?".to_string(), true, 10);
+    assert!(result.is_ok(), "result.is_ok() not true. name: '{}'", name);
+}
+
+#[rstest]
+fn install_option_try_new_err(
+    #[values(("", "Install?", 10), ("a b", "Install?", 10), ("extra", "", 10), ("extra", "Install?", 0))]
+    input: (&str, &str, u32),
+) {
+    let (name, prompt, timeout) = input;
+    let result = InstallOption::try_new(name.to_string(), prompt.to_string(), true, timeout);
+    assert!(
+        result.is_err(),
+        "result.is_err() not true. name: '{}'",
+        name
+    );
+}
+
+#[rstest]
+fn requires_validate_err(
+    #[values(
+        |r: &mut Requires| r.abis = vec![],
+        |r: &mut Requires| r.abis = vec!["mips".to_string()],
+        |r: &mut Requires| r.modules = vec!["1_module".to_string()],
+        |r: &mut Requires| r.conflicts = vec!["a module".to_string()],
+        |r: &mut Requires| {
+            r.modules = vec!["a_module".to_string()];
+            r.conflicts = vec!["a_module".to_string()];
+        },
+    )]
+    modify: fn(&mut Requires),
+) {
+    let mut requires = Requires::new(&Target::Arm64V8a);
+    assert!(requires.validate().is_ok());
+    modify(&mut requires);
+    assert!(requires.validate().is_err(), "requires: {:?}", requires);
+}
+
+#[rstest]
+#[case(Manager::Magisk, "system/app/Bloat", "/system/app/Bloat")]
+#[case(Manager::Magisk, "system/etc/hosts", "/system/etc/hosts")]
+#[case(Manager::Magisk, "system/priv-app/Foo/", "/system/priv-app/Foo")]
+#[case(Manager::Magisk, "vendor/app/Bloat", "/system/vendor/app/Bloat")]
+#[case(Manager::Magisk, "odm/etc/foo.xml", "/system/vendor/odm/etc/foo.xml")]
+#[case(Manager::KernelSu, "vendor/app/Bloat", "/vendor/app/Bloat")]
+fn parse_device_path_ok(#[case] manager: Manager, #[case] path: &str, #[case] expected: &str) {
+    assert_eq!(
+        parse_device_path(path, "remove", &manager).unwrap(),
+        expected
+    );
+}
+
+#[rstest]
+fn parse_device_path_magisk_err() {
+    let result = parse_device_path("oem/app/Foo", "remove", &Manager::Magisk);
+    assert!(result.is_err());
+}
+
+#[rstest]
+fn parse_device_path_err(
+    #[values(
+        "",
+        "/system/app",
+        "system/../app",
+        "./system/app",
+        "system/my app",
+        "data/app/Foo",
+        "apex/com.android.foo",
+        "webroot/index.html"
+    )]
+    path: &str,
+    #[values(Manager::Magisk, Manager::KernelSu)] manager: Manager,
+) {
+    let result = parse_device_path(path, "remove", &manager);
+    assert!(
+        result.is_err(),
+        "result.is_err() not true. path: '{}'",
+        path
+    );
+}
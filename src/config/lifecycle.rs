@@ -0,0 +1,241 @@
+#[cfg(test)]
+mod test;
+
+use super::version_code::VersionCode;
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Stage {
+    PostFsData,
+    Service,
+    Uninstall,
+}
+
+impl TryFrom<&str> for Stage {
+    type Error = anyhow::Error;
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        match name {
+            "post-fs-data" => Ok(Stage::PostFsData),
+            "service" => Ok(Stage::Service),
+            "uninstall" => Ok(Stage::Uninstall),
+            _ => bail!("Invalid stage: {}", name),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ScriptEntry {
+    pub binary: String,
+    pub args: Vec<String>,
+    pub stage: Stage,
+    pub wait_for_boot: bool,
+    pub log: Option<String>,
+}
+
+impl ScriptEntry {
+    pub fn try_new(
+        binary: String,
+        args: Vec<String>,
+        stage: &str,
+        wait_for_boot: bool,
+        log: Option<String>,
+    ) -> Result<Self> {
+        check_binary(&binary)?;
+        let stage = Stage::try_from(stage)?;
+        if let Some(value) = &log
+            && !Path::new(value).is_absolute()
+        {
+            bail!("Invalid log: path must be absolute");
+        }
+
+        Ok(Self {
+            binary,
+            args,
+            stage,
+            wait_for_boot,
+            log,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RestartPolicy {
+    Never,
+    OnFailure,
+    Always,
+}
+
+impl RestartPolicy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RestartPolicy::Never => "never",
+            RestartPolicy::OnFailure => "on-failure",
+            RestartPolicy::Always => "always",
+        }
+    }
+}
+
+impl TryFrom<&str> for RestartPolicy {
+    type Error = anyhow::Error;
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        match name {
+            "never" => Ok(RestartPolicy::Never),
+            "on-failure" => Ok(RestartPolicy::OnFailure),
+            "always" => Ok(RestartPolicy::Always),
+            _ => bail!("Invalid restart policy: {}", name),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Service {
+    pub name: String,
+    pub binary: String,
+    pub args: Vec<String>,
+    pub user: Option<String>,
+    pub restart: RestartPolicy,
+    pub backoff: u32,
+    pub max_backoff: u32,
+    pub log: Option<String>,
+}
+
+impl Service {
+    pub fn validate(&self) -> Result<()> {
+        let re_name = Regex::new(r"^[a-zA-Z0-9][a-zA-Z0-9._-]*$")?;
+        if !re_name.is_match(&self.name) {
+            bail!("Invalid service name: '{}'", self.name);
+        }
+
+        check_binary(&self.binary)?;
+
+        if let Some(value) = &self.user {
+            let re_user = Regex::new(r"^([a-z_][a-z0-9_]*|[0-9]+)$")?;
+            if !re_user.is_match(value) {
+                bail!("Invalid user: '{}'", value);
+            }
+        }
+
+        if self.backoff == 0 {
+            bail!("Invalid backoff: value must be at least 1 second");
+        }
+
+        if self.max_backoff < self.backoff {
+            bail!("Invalid max_backoff: value is less than backoff");
+        }
+
+        if let Some(value) = &self.log
+            && !Path::new(value).is_absolute()
+        {
+            bail!("Invalid log: path must be absolute");
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct DataDir {
+    pub path: String,
+    pub clean_on_uninstall: bool,
+    pub migrations: Vec<Migration>,
+}
+
+impl DataDir {
+    pub const MIGRATIONS: &str = "migrations";
+    const ROOT: &str = "/data/adb";
+    /// Directories the root managers and their boot scripts own.
+    const RESERVED: &[&str] = &["magisk", "ksu", "ap", "service.d", "post-fs-data.d"];
+
+    pub fn try_new(path: String, clean_on_uninstall: bool) -> Result<Self> {
+        let data_path = Path::new(&path);
+        if !data_path.is_absolute() {
+            bail!("Invalid data_dir: path must be absolute");
+        }
+        for comp in data_path.components() {
+            if matches!(comp, Component::ParentDir | Component::CurDir) {
+                bail!("Invalid data_dir: contains '.' or '..'");
+            }
+        }
+        // The directory is removed with `rm -rf` on uninstall, keep it to a
+        // directory of its own.
+        let Some(name) = data_path
+            .strip_prefix(Self::ROOT)
+            .ok()
+            .and_then(|value| value.components().next())
+        else {
+            bail!("Invalid data_dir: path must be inside {}/", Self::ROOT);
+        };
+        let name = name.as_os_str().to_string_lossy();
+        if name.starts_with("modules") {
+            bail!("Invalid data_dir: path is a module directory");
+        }
+        if Self::RESERVED.contains(&name.as_ref()) {
+            bail!("Invalid data_dir: {}/{} is reserved", Self::ROOT, name);
+        }
+
+        Ok(Self {
+            path,
+            clean_on_uninstall,
+            migrations: vec![],
+        })
+    }
+
+    /// Reads `<version>.sh` migration scripts from `dir`, sorted by version.
+    /// Every migration must target a version not newer than `version_code`.
+    pub fn load_migrations(&mut self, dir: &Path, version_code: &VersionCode) -> Result<()> {
+        if !dir.is_dir() {
+            return Ok(());
+        }
+
+        let mut migrations: Vec<Migration> = vec![];
+        for entry in fs::read_dir(dir).context("Failed read migrations")? {
+            let source = entry?.path();
+            let name = source
+                .file_name()
+                .and_then(|value| value.to_str())
+                .unwrap_or_default();
+            let Some(version) = name.strip_suffix(".sh") else {
+                bail!("Invalid migration: '{}' is not a .sh file", name);
+            };
+            let migration_code = VersionCode::try_from(version)
+                .with_context(|| format!("Invalid migration: '{}'", name))?;
+            if &migration_code > version_code {
+                bail!("Invalid migration: '{}' is newer than the module", name);
+            }
+            if migrations
+                .iter()
+                .any(|value| value.version_code == migration_code)
+            {
+                bail!("Invalid migration: '{}' duplicates a version", name);
+            }
+            migrations.push(Migration {
+                version_code: migration_code,
+                source,
+            });
+        }
+        migrations.sort_by(|a, b| a.version_code.cmp(&b.version_code));
+        self.migrations = migrations;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Migration {
+    pub version_code: VersionCode,
+    pub source: PathBuf,
+}
+
+pub(super) fn check_binary(binary: &str) -> Result<()> {
+    let mut components = Path::new(binary).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(name)), None) if name != "..." => Ok(()),
+        _ => bail!("Invalid binary: '{}' is not a file name", binary),
+    }
+}
@@ -0,0 +1,162 @@
+use super::*;
+use rstest::rstest;
+
+#[rstest]
+#[case("post-fs-data", Stage::PostFsData)]
+#[case("service", Stage::Service)]
+#[case("uninstall", Stage::Uninstall)]
+fn stage_from_str(#[case] name: &str, #[case] expected: Stage) {
+    assert_eq!(Stage::try_from(name).unwrap(), expected);
+}
+
+#[rstest]
+fn script_entry_try_new_err(
+    #[values(
+        ("", "service", None),
+        ("bin/daemon", "service", None),
+        ("..", "service", None),
+        ("daemon", "late_start", None),
+        ("daemon", "service", Some("daemon.log")),
+    )]
+    input: (&str, &str, Option<&str>),
+) {
+    let (binary, stage, log) = input;
+    let result = ScriptEntry::try_new(
+        binary.to_string(),
+        vec![],
+        stage,
+        false,
+        log.map(str::to_string),
+    );
+    assert!(
+        result.is_err(),
+        "result.is_err() not true. binary: '{}', stage: '{}'",
+        binary,
+        stage
+    );
+}
+
+#[rstest]
+#[case("never", RestartPolicy::Never)]
+#[case("on-failure", RestartPolicy::OnFailure)]
+#[case("always", RestartPolicy::Always)]
+fn restart_policy_from_str(#[case] name: &str, #[case] expected: RestartPolicy) {
+    let policy = RestartPolicy::try_from(name).unwrap();
+    assert_eq!(policy, expected);
+    assert_eq!(policy.name(), name);
+}
+
+#[rstest]
+fn service_validate(#[values(None, Some("root"), Some("shell"), Some("2000"))] user: Option<&str>) {
+    let mut service = service();
+    service.user = user.map(str::to_string);
+    assert!(service.validate().is_ok(), "user: {:?}", user);
+}
+
+#[rstest]
+fn service_validate_err(
+    #[values(
+        |s: &mut Service| s.name = "".to_string(),
+        |s: &mut Service| s.name = "my daemon".to_string(),
+        |s: &mut Service| s.binary = "bin/daemon".to_string(),
+        |s: &mut Service| s.user = Some("Root User".to_string()),
+        |s: &mut Service| s.backoff = 0,
+        |s: &mut Service| s.max_backoff = 0,
+        |s: &mut Service| s.log = Some("daemon.log".to_string()),
+    )]
+    modify: fn(&mut Service),
+) {
+    let mut service = service();
+    modify(&mut service);
+    assert!(service.validate().is_err(), "service: {:?}", service);
+}
+
+fn service() -> Service {
+    Service {
+        name: "daemon".to_string(),
+        binary: "daemon".to_string(),
+        args: vec![],
+        user: None,
+        restart: RestartPolicy::OnFailure,
+        backoff: 1,
+        max_backoff: 60,
+        log: None,
+    }
+}
+
+#[rstest]
+fn data_dir_try_new(
+    #[values(
+        "/data/adb/a_module",
+        "/data/adb/a_module/",
+        "/data/adb/a_module/state"
+    )]
+    path: &str,
+) {
+    let data_dir = DataDir::try_new(path.to_string(), true).unwrap();
+    assert_eq!(data_dir.path, path);
+}
+
+#[rstest]
+fn data_dir_try_new_err(
+    #[values(
+        "",
+        "data/adb/a_module",
+        "/",
+        "/data/adb/../a_module",
+        "/data",
+        "/data/adb",
+        "/data/adb/",
+        "/sdcard",
+        "/data/adbx/a_module",
+        "/data/adb/modules",
+        "/data/adb/modules/a_module",
+        "/data/adb/magisk",
+        "/data/adb/ksu",
+        "/data/adb/ap",
+        "/data/adb/service.d",
+        "/data/adb/post-fs-data.d",
+        "/data/adb/magisk/a_module",
+        "/data/adb/modules_update"
+    )]
+    path: &str,
+) {
+    let result = DataDir::try_new(path.to_string(), false);
+    assert!(
+        result.is_err(),
+        "result.is_err() not true. path: '{}'",
+        path
+    );
+}
+
+#[rstest]
+fn data_dir_load_migrations() {
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["1.2.0.sh", "1.10.0.sh", "1.2.0-beta.1.sh"] {
+        fs::write(dir.path().join(name), "").unwrap();
+    }
+    let mut data_dir = DataDir::try_new("/data/adb/a_module".to_string(), false).unwrap();
+    let version_code = VersionCode::try_from("1.10.0").unwrap();
+    data_dir.load_migrations(dir.path(), &version_code).unwrap();
+
+    let codes: Vec<String> = data_dir
+        .migrations
+        .iter()
+        .map(|value| value.version_code.to_string())
+        .collect();
+    assert_eq!(codes, ["010200201", "010200900", "011000900"]);
+}
+
+#[rstest]
+fn data_dir_load_migrations_err(#[values("1.2.sh", "1.2.0.txt", "2.0.0.sh")] name: &str) {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join(name), "").unwrap();
+    let mut data_dir = DataDir::try_new("/data/adb/a_module".to_string(), false).unwrap();
+    let version_code = VersionCode::try_from("1.10.0").unwrap();
+    let result = data_dir.load_migrations(dir.path(), &version_code);
+    assert!(
+        result.is_err(),
+        "result.is_err() not true. name: '{}'",
+        name
+    );
+}
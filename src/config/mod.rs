@@ -1,70 +1,490 @@
+mod app;
+mod generator;
+mod hooks;
+mod installer;
+mod lifecycle;
+pub(crate) mod pattern;
 #[cfg(test)]
 mod test;
 mod toml_types;
 mod version_code;
+mod workspace;
 
-use crate::project::{ManifestProvider, ProjectProvider};
+use crate::project::{Manager, ManifestProvider, ProjectProvider, Target};
+use crate::template;
 use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use globset::GlobSet;
 use regex::Regex;
 use std::{
+    collections::BTreeMap,
     fmt::Display,
     fs,
     path::{Component, Path, PathBuf},
     rc::Rc,
 };
-use version_code::VersionCode;
+pub use version_code::VersionCode;
+
+pub use app::App;
+pub use generator::Generator;
+pub use hooks::{HookStage, Hooks};
+pub use installer::{InstallOption, Requires, parse_device_path};
+pub use lifecycle::{DataDir, RestartPolicy, ScriptEntry, Service, Stage};
+
+/// Standalone module config read instead of `[package.metadata.magisk]`.
+pub const CONFIG_FILE: &str = "Magisk.toml";
+
+/// Package fields from `cargo metadata`, with `field.workspace = true`
+/// already resolved by cargo.
+#[derive(Debug, Clone, Default)]
+pub struct PackageInfo {
+    pub name: String,
+    pub version: String,
+    pub authors: Vec<String>,
+    pub description: Option<String>,
+    /// `[workspace.metadata.magisk]`, merged under the package config.
+    pub defaults: Option<toml::Table>,
+}
 
 #[derive(Debug)]
 pub struct Config {
     pub module_prop: ModuleProp,
     pub assets: Vec<Asset>,
+    pub webui: Option<WebUi>,
+    pub scripts: Vec<ScriptEntry>,
+    pub services: Vec<Service>,
+    pub bootloop_guard: Option<u32>,
+    pub data_dir: Option<DataDir>,
+    pub options: Vec<InstallOption>,
+    pub requires: Requires,
+    /// Device paths hidden from the system, e.g. `/system/app/Bloat`.
+    pub remove: Vec<String>,
+    /// Device directories whose content is replaced by the module.
+    pub replace: Vec<String>,
+    pub apps: Vec<App>,
+    /// Patterns from `.magiskignore`, matched against project relative paths
+    /// of files found by glob and directory assets.
+    pub ignore: GlobSet,
+    /// Custom variables for templated assets.
+    pub vars: BTreeMap<String, String>,
+    pub hooks: Hooks,
+    /// Cargo features enabled by the built variant.
+    pub features: Vec<String>,
+    /// Names of all variants declared in the config.
+    pub variants: Vec<String>,
 }
 
 impl Config {
     pub fn load(
         manifest_provider: &Rc<dyn ManifestProvider>,
+        config_path: Option<PathBuf>,
         project_provider: &Rc<dyn ProjectProvider>,
+        package: &PackageInfo,
     ) -> Result<Self> {
         let manifest_path = manifest_provider.find_manifest_path()?;
-        Self::load_with_path_unchecked(manifest_path, project_provider)
+        Self::load_with_path(manifest_path, config_path, project_provider, package)
     }
 
     pub fn load_with_path(
         manifest_path: PathBuf,
+        config_path: Option<PathBuf>,
         project_provider: &Rc<dyn ProjectProvider>,
+        package: &PackageInfo,
     ) -> Result<Self> {
         if !manifest_path.exists() {
             bail!("Invalid manifest path: file not exists");
         }
 
-        Self::load_with_path_unchecked(manifest_path, project_provider)
-    }
-
-    fn load_with_path_unchecked(
-        manifest_path: PathBuf,
-        project_provider: &Rc<dyn ProjectProvider>,
-    ) -> Result<Self> {
         let manifest_content =
-            fs::read_to_string(manifest_path).context("Failed read Cargo.toml")?;
+            fs::read_to_string(&manifest_path).context("Failed read Cargo.toml")?;
         let config: toml_types::Manifest =
             toml::from_str(&manifest_content).context("Invalid Cargo.toml: failed parse")?;
+        let metadata = config.package.metadata.and_then(|value| value.magisk);
+        let default_path = manifest_path
+            .parent()
+            .context("Invalid manifest path: no parent directory")?
+            .join(CONFIG_FILE);
+        let (selected, warnings) =
+            Self::select_config(config_path, &default_path, metadata.is_some())?;
+        for warning in warnings {
+            eprintln!("warning: {}", warning);
+        }
+        let (table, label) = match selected {
+            Some(path) => {
+                let content = fs::read_to_string(&path)
+                    .with_context(|| format!("Failed read '{}'", path.display()))?;
+                let table: toml::Table = toml::from_str(&content)
+                    .with_context(|| format!("Invalid '{}': failed parse", path.display()))?;
+                (table, path.display().to_string())
+            }
+            None => (
+                metadata.context("Invalid Cargo.toml: missing [package.metadata.magisk]")?,
+                "[package.metadata.magisk]".to_string(),
+            ),
+        };
+        let table = match &package.defaults {
+            Some(defaults) => workspace::merge(defaults.clone(), table),
+            None => table,
+        };
+        let mut magisk: toml_types::Magisk = toml::Value::Table(table)
+            .try_into()
+            .with_context(|| format!("Invalid {}: failed parse", label))?;
+        let variants: Vec<String> = magisk.variants.keys().cloned().collect();
+        let features = Self::apply_variant(&mut magisk, project_provider.get_variant())?;
+        Self::apply_target_override(&mut magisk, project_provider.get_target())?;
 
+        let author = match magisk.author {
+            Some(value) => value,
+            None if !package.authors.is_empty() => package.authors.join(", "),
+            None => bail!("Invalid config: author is not set and package.authors is empty"),
+        };
         let module_prop = ModuleProp::new(
-            config.package.metadata.magisk.id,
-            config.package.metadata.magisk.name,
-            config.package.version,
-            config.package.metadata.magisk.author,
-        )?;
+            magisk.id,
+            magisk.name.unwrap_or_else(|| package.name.clone()),
+            package.version.clone(),
+            author,
+            // Cargo descriptions may span lines, module.prop values may not.
+            magisk.description.or(package
+                .description
+                .as_ref()
+                .map(|value| value.split_whitespace().collect::<Vec<_>>().join(" "))),
+        )?
+        .with_props(magisk.props)?;
+        let requires = match magisk.requires {
+            Some(value) => Requires {
+                min_api: value.min_api,
+                min_magisk: value.min_magisk,
+                min_ksu: value.min_ksu,
+                abis: value
+                    .abis
+                    .unwrap_or_else(|| vec![project_provider.get_target().abi().to_string()]),
+                modules: value.modules,
+                conflicts: value.conflicts,
+            },
+            None => Requires::new(project_provider.get_target()),
+        };
+        requires
+            .validate()
+            .context("Failed to initialize requires")?;
+        let mut remove: Vec<String> = vec![];
+        for path in magisk.remove {
+            remove.push(parse_device_path(
+                &path,
+                "remove",
+                project_provider.get_manager(),
+            )?);
+        }
+        let mut replace: Vec<String> = vec![];
+        for path in magisk.replace {
+            replace.push(parse_device_path(
+                &path,
+                "replace",
+                project_provider.get_manager(),
+            )?);
+        }
+        let hooks = Self::parse_hooks(magisk.hooks)?;
+        let vars = magisk.vars;
+        for name in vars.keys() {
+            template::check_name(name)?;
+        }
+        let ignore_path = project_provider
+            .get_project_path()?
+            .join(pattern::IGNORE_FILE);
+        let ignore = match ignore_path.is_file() {
+            true => {
+                let content = fs::read_to_string(&ignore_path)
+                    .with_context(|| format!("Failed read {}", pattern::IGNORE_FILE))?;
+                pattern::compile(&pattern::parse_ignore(&content)?)?
+            }
+            false => GlobSet::empty(),
+        };
+        let mut apps: Vec<App> = vec![];
+        for app in magisk.apps {
+            let app = App::try_new(
+                app.apk,
+                app.name,
+                app.privileged,
+                app.permissions,
+                project_provider,
+                &module_prop,
+            )?;
+            if apps.iter().any(|value| value.name == app.name) {
+                bail!("Invalid app name: '{}' is not unique", app.name);
+            }
+            apps.push(app);
+        }
+        let mut options: Vec<InstallOption> = vec![];
+        for option in magisk.options {
+            let option = InstallOption::try_new(
+                option.name,
+                option.prompt,
+                option.default,
+                option.timeout.unwrap_or(10),
+            )?;
+            if options.iter().any(|value| value.name == option.name) {
+                bail!("Invalid option name: '{}' is not unique", option.name);
+            }
+            options.push(option);
+        }
         let mut assets: Vec<Asset> = vec![];
-        for asset in config.package.metadata.magisk.assets {
-            assets.push(Asset::try_new(asset.source, asset.dest, project_provider)?);
+        for asset in magisk.assets {
+            if let Some(name) = &asset.option
+                && !options.iter().any(|value| &value.name == name)
+            {
+                bail!("Invalid asset option: '{}' is not declared", name);
+            }
+            let enabled = Asset::is_enabled(&asset, project_provider)?;
+            let asset = Asset::try_new(asset, project_provider, &module_prop)?;
+            if enabled {
+                assets.push(asset);
+            }
+        }
+        let webui = match magisk.webui {
+            Some(value) => Some(WebUi::try_new(
+                value.source,
+                value.build,
+                project_provider,
+                &module_prop,
+            )?),
+            None => None,
+        };
+        let mut scripts: Vec<ScriptEntry> = vec![];
+        for script in magisk.scripts {
+            scripts.push(
+                ScriptEntry::try_new(
+                    script.binary,
+                    script.args,
+                    &script.stage,
+                    script.wait_for_boot,
+                    script.log,
+                )
+                .context("Failed to initialize script")?,
+            );
+        }
+        let mut services: Vec<Service> = vec![];
+        for service in magisk.services {
+            let restart = match service.restart {
+                Some(value) => RestartPolicy::try_from(value.as_str())?,
+                None => RestartPolicy::OnFailure,
+            };
+            let service = Service {
+                name: service.name.unwrap_or_else(|| service.binary.clone()),
+                binary: service.binary,
+                args: service.args,
+                user: service.user,
+                restart,
+                backoff: service.backoff.unwrap_or(1),
+                max_backoff: service.max_backoff.unwrap_or(60),
+                log: service.log,
+            };
+            service.validate().context("Failed to initialize service")?;
+            if services.iter().any(|value| value.name == service.name) {
+                bail!("Invalid service name: '{}' is not unique", service.name);
+            }
+            services.push(service);
+        }
+        let bootloop_guard = magisk.bootloop_guard;
+        if bootloop_guard == Some(0) {
+            bail!("Invalid bootloop_guard: value must be at least 1");
         }
+        let data_dir = match magisk.data_dir {
+            Some(value) => {
+                let path = value
+                    .path
+                    .unwrap_or_else(|| format!("/data/adb/{}", module_prop.id));
+                let mut data_dir = DataDir::try_new(path, value.clean_on_uninstall)?;
+                let migrations_path = project_provider
+                    .get_project_path()?
+                    .join(DataDir::MIGRATIONS);
+                data_dir.load_migrations(&migrations_path, &module_prop.version_code)?;
+                Some(data_dir)
+            }
+            None => None,
+        };
 
         Ok(Self {
             module_prop,
             assets,
+            webui,
+            scripts,
+            services,
+            bootloop_guard,
+            data_dir,
+            options,
+            requires,
+            remove,
+            replace,
+            apps,
+            ignore,
+            vars,
+            hooks,
+            features,
+            variants,
         })
     }
+
+    /// Picks where the module config is read from: `--config` first, then
+    /// `Magisk.toml` next to Cargo.toml, then `[package.metadata.magisk]`.
+    /// Returns `None` for the package metadata, and a warning for each
+    /// config that is shadowed by the chosen one.
+    fn select_config(
+        config_path: Option<PathBuf>,
+        default_path: &Path,
+        has_metadata: bool,
+    ) -> Result<(Option<PathBuf>, Vec<String>)> {
+        let has_default = default_path.is_file();
+        let mut warnings: Vec<String> = vec![];
+        if let Some(path) = config_path {
+            if !path.is_file() {
+                bail!("Invalid config path: '{}' not exists", path.display());
+            }
+            let same_file =
+                has_default && fs::canonicalize(&path).ok() == fs::canonicalize(default_path).ok();
+            if has_default && !same_file {
+                warnings.push(format!(
+                    "'{}' shadows '{}'",
+                    path.display(),
+                    default_path.display()
+                ));
+            }
+            if has_metadata {
+                warnings.push(format!(
+                    "'{}' shadows [package.metadata.magisk]",
+                    path.display()
+                ));
+            }
+            return Ok((Some(path), warnings));
+        }
+
+        if has_default {
+            if has_metadata {
+                warnings.push(format!(
+                    "'{}' shadows [package.metadata.magisk]",
+                    default_path.display()
+                ));
+            }
+            return Ok((Some(default_path.to_path_buf()), warnings));
+        }
+
+        if !has_metadata {
+            bail!(
+                "Invalid Cargo.toml: missing [package.metadata.magisk] and no {}",
+                CONFIG_FILE
+            );
+        }
+        Ok((None, warnings))
+    }
+
+    /// Merges the `[package.metadata.magisk.variants.<name>]` table of the
+    /// built variant into `magisk` and returns its cargo features. Its
+    /// assets replace the package assets and its props are added to the
+    /// package props. Target tables are applied on top of the variant.
+    fn apply_variant(
+        magisk: &mut toml_types::Magisk,
+        variant: Option<&str>,
+    ) -> Result<Vec<String>> {
+        let mut variants = std::mem::take(&mut magisk.variants);
+        for name in variants.keys() {
+            check_variant(name)?;
+        }
+        let Some(variant) = variant else {
+            return Ok(vec![]);
+        };
+        let Some(value) = variants.remove(variant) else {
+            bail!("Invalid variant: '{}' is not declared", variant);
+        };
+
+        if let Some(id) = value.id {
+            magisk.id = id;
+        }
+        if value.name.is_some() {
+            magisk.name = value.name;
+        }
+        if value.description.is_some() {
+            magisk.description = value.description;
+        }
+        if let Some(assets) = value.assets {
+            magisk.assets = assets;
+        }
+        magisk.props.extend(value.props);
+        for feature in &value.features {
+            if feature.is_empty() || feature.contains([',', ' ']) {
+                bail!("Invalid variant feature: '{}'", feature);
+            }
+        }
+        Ok(value.features)
+    }
+
+    /// Merges the `[package.metadata.magisk.target.<triple>]` table of the
+    /// built target into `magisk`. Its assets are added and its metadata
+    /// replaces the package values.
+    fn apply_target_override(magisk: &mut toml_types::Magisk, target: &Target) -> Result<()> {
+        let mut selected = None;
+        for (name, value) in std::mem::take(&mut magisk.target) {
+            let value_target = parse_target(&name).context("Invalid target table")?;
+            if &value_target == target {
+                if selected.is_some() {
+                    bail!("Invalid target table: '{}' is declared twice", target);
+                }
+                selected = Some(value);
+            }
+        }
+
+        let Some(value) = selected else {
+            return Ok(());
+        };
+        if value.name.is_some() {
+            magisk.name = value.name;
+        }
+        if value.author.is_some() {
+            magisk.author = value.author;
+        }
+        if value.description.is_some() {
+            magisk.description = value.description;
+        }
+        magisk.assets.extend(value.assets);
+        Ok(())
+    }
+
+    fn parse_hooks(hooks: toml_types::Hooks) -> Result<Hooks> {
+        let convert = |commands: Vec<toml_types::HookCommand>| -> Vec<Vec<String>> {
+            commands
+                .into_iter()
+                .map(|command| match command {
+                    toml_types::HookCommand::Shell(value) => hooks::shell_command(value),
+                    toml_types::HookCommand::Args(value) => value,
+                })
+                .collect()
+        };
+        let result = Hooks {
+            pre_build: convert(hooks.pre_build),
+            post_build: convert(hooks.post_build),
+            pre_package: convert(hooks.pre_package),
+            post_package: convert(hooks.post_package),
+        };
+        result.validate().context("Failed to initialize hooks")?;
+        Ok(result)
+    }
+
+    /// Returns the unique names of cargo binaries staged into the module.
+    pub fn binaries(&self) -> Vec<&str> {
+        let mut result: Vec<&str> = vec![];
+        let names = self.scripts.iter().map(|value| value.binary.as_str());
+        let names = names.chain(self.services.iter().map(|value| value.binary.as_str()));
+        for name in names {
+            if !result.contains(&name) {
+                result.push(name);
+            }
+        }
+        result
+    }
+
+    pub fn data_path(&self) -> String {
+        match &self.data_dir {
+            Some(value) => value.path.clone(),
+            None => format!("/data/adb/{}", self.module_prop.id),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -74,11 +494,34 @@ pub struct ModuleProp {
     pub version: String,
     pub version_code: VersionCode,
     pub author: String,
+    pub description: Option<String>,
+    /// Extra module.prop entries, e.g. `updateJson`.
+    pub props: BTreeMap<String, String>,
 }
 
 impl ModuleProp {
-    pub fn new(id: String, name: String, version: String, author: String) -> Result<Self> {
+    const RESERVED: [&str; 6] = [
+        "id",
+        "name",
+        "version",
+        "versionCode",
+        "author",
+        "description",
+    ];
+
+    pub fn new(
+        id: String,
+        name: String,
+        version: String,
+        author: String,
+        description: Option<String>,
+    ) -> Result<Self> {
         Self::validate(&id, &name, &version, &author)?;
+        if let Some(value) = &description
+            && value.contains('\n')
+        {
+            bail!("Invalid description: value contains a line break");
+        }
         let version_code = VersionCode::try_from(version.as_str())?;
 
         Ok(Self {
@@ -87,18 +530,30 @@ impl ModuleProp {
             version,
             version_code,
             author,
+            description,
+            props: BTreeMap::new(),
         })
     }
 
-    fn validate(id: &str, name: &str, version: &str, author: &str) -> Result<()> {
-        if id.is_empty() {
-            bail!("Invalid id: value is empty");
+    pub fn with_props(mut self, props: BTreeMap<String, String>) -> Result<Self> {
+        let regex = Regex::new(r"^[a-zA-Z][a-zA-Z0-9._]*$")?;
+        for (key, value) in &props {
+            if !regex.is_match(key) {
+                bail!("Invalid prop key: '{}'", key);
+            }
+            if Self::RESERVED.contains(&key.as_str()) {
+                bail!("Invalid prop key: '{}' is reserved", key);
+            }
+            if value.contains('\n') {
+                bail!("Invalid prop '{}': value contains a line break", key);
+            }
         }
+        self.props = props;
+        Ok(self)
+    }
 
-        let re_id = Regex::new(r"^[a-zA-Z][a-zA-Z0-9._-]+$")?;
-        if !re_id.is_match(id) {
-            bail!("Invalid id: unsupported format");
-        }
+    fn validate(id: &str, name: &str, version: &str, author: &str) -> Result<()> {
+        check_id(id)?;
 
         if name.is_empty() {
             bail!("Invalid name: value is empty");
@@ -116,6 +571,49 @@ impl ModuleProp {
     }
 }
 
+impl ModuleProp {
+    pub fn to_js(&self) -> String {
+        let value = serde_json::json!({
+            "id": self.id,
+            "name": self.name,
+            "version": self.version,
+            "versionCode": self.version_code.value(),
+            "author": self.author,
+        });
+        format!("window.MODULE = Object.freeze({});\n", value)
+    }
+}
+
+/// Parses a target triple or ABI name, e.g. `aarch64-linux-android` or
+/// `arm64-v8a`.
+fn parse_target(name: &str) -> Result<Target> {
+    match Target::from_str(name, false) {
+        Ok(value) => Ok(value),
+        Err(_) => bail!("unknown target '{}'", name),
+    }
+}
+
+fn check_id(id: &str) -> Result<()> {
+    if id.is_empty() {
+        bail!("Invalid id: value is empty");
+    }
+
+    let re_id = Regex::new(r"^[a-zA-Z][a-zA-Z0-9._-]+$")?;
+    if !re_id.is_match(id) {
+        bail!("Invalid id: unsupported format");
+    }
+    Ok(())
+}
+
+/// Variant names become staging directory names.
+fn check_variant(name: &str) -> Result<()> {
+    let re_name = Regex::new(r"^[a-zA-Z0-9][a-zA-Z0-9_-]*$")?;
+    if !re_name.is_match(name) {
+        bail!("Invalid variant name: '{}'", name);
+    }
+    Ok(())
+}
+
 impl Display for ModuleProp {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(
@@ -126,32 +624,145 @@ impl Display for ModuleProp {
              version={}\n\
              versionCode={}",
             self.id, self.name, self.author, self.version, self.version_code
-        )
+        )?;
+        if let Some(value) = &self.description {
+            writeln!(f, "description={}", value)?;
+        }
+        for (key, value) in &self.props {
+            writeln!(f, "{}={}", key, value)?;
+        }
+        Ok(())
     }
 }
 
 #[derive(Debug)]
 pub struct Asset {
-    pub source: PathBuf,
+    pub source: AssetSource,
     pub dest: PathBuf,
+    /// Name of the install option that has to be chosen for this asset
+    /// to be installed.
+    pub option: Option<String>,
+    /// Files to leave out when a glob or directory source is expanded.
+    pub exclude: GlobSet,
+    /// Copy the content of a directory source into `dest` instead of the
+    /// directory itself. Set by `contents = true` or a trailing `/`.
+    pub contents: bool,
+    pub symlinks: Symlinks,
+    /// Allow this asset to replace files staged by earlier assets.
+    pub overwrite: bool,
+    /// Substitute `{{ name }}` variables in the staged file.
+    pub template: bool,
 }
 
 impl Asset {
-    pub fn try_new(
-        source: String,
-        dest: String,
+    const UNSUPPORTED_ROOTS: [&str; 8] = [
+        "data", "apex", "cache", "metadata", "mnt", "dev", "proc", "sys",
+    ];
+
+    fn try_new(
+        asset: toml_types::Asset,
         provider: &Rc<dyn ProjectProvider>,
+        module_prop: &ModuleProp,
     ) -> Result<Self> {
-        let source = Self::parse_source(source, provider).context("Failed to initialize Asset")?;
-        let dest = Self::parse_dest(dest, provider).context("Failed to initialize Asset")?;
-        Ok(Self { source, dest })
+        let (source, contents) = match (asset.source, asset.content, asset.generate) {
+            (Some(toml_types::Source::Path(source)), None, None) => {
+                let contents = asset.contents || source.ends_with('/');
+                let source = Self::parse_source(source, provider, module_prop)
+                    .context("Failed to initialize Asset")?;
+                (AssetSource::Path(source), contents)
+            }
+            (Some(toml_types::Source::OutDir { out_dir }), None, None) => {
+                let contents = asset.contents || out_dir.ends_with('/');
+                let source = Self::parse_out_dir(out_dir, provider, module_prop)
+                    .context("Failed to initialize Asset")?;
+                (AssetSource::OutDir(source), contents)
+            }
+            (None, Some(content), None) => (AssetSource::Content(content), false),
+            (None, None, Some(generate)) => {
+                let contents = asset.contents || generate.output.ends_with('/');
+                let generator = Generator::try_new(
+                    generate.command,
+                    generate.output,
+                    generate.inputs,
+                    provider,
+                    module_prop,
+                )
+                .context("Failed to initialize Asset")?;
+                (AssetSource::Generate(generator), contents)
+            }
+            (None, None, None) => {
+                bail!("Failed to initialize Asset: source, content or generate is required")
+            }
+            _ => bail!(
+                "Failed to initialize Asset: source, content and generate are mutually exclusive"
+            ),
+        };
+        let dest = Self::parse_dest(asset.dest, provider, module_prop)
+            .context("Failed to initialize Asset")?;
+        let exclude = pattern::compile(&asset.exclude).context("Failed to initialize Asset")?;
+        let symlinks = match asset.symlinks {
+            Some(value) => {
+                Symlinks::try_from(value.as_str()).context("Failed to initialize Asset")?
+            }
+            None => Symlinks::Preserve,
+        };
+        let result = Self {
+            source,
+            dest,
+            option: asset.option,
+            exclude,
+            contents,
+            symlinks,
+            overwrite: asset.overwrite,
+            template: asset.template,
+        };
+        let is_glob = match &result.source {
+            AssetSource::Path(value) | AssetSource::OutDir(value) => {
+                pattern::split_glob(value).is_some()
+            }
+            AssetSource::Generate(value) => pattern::split_glob(&value.output).is_some(),
+            AssetSource::Content(_) => false,
+        };
+        if result.template && is_glob {
+            bail!("Failed to initialize Asset: template requires a single file source");
+        }
+        Ok(result)
     }
 
-    fn parse_source(source: String, provider: &Rc<dyn ProjectProvider>) -> Result<PathBuf> {
+    /// Checks the `targets` and `profiles` filters of `asset` against the
+    /// current build. Missing filters match every build.
+    fn is_enabled(asset: &toml_types::Asset, provider: &Rc<dyn ProjectProvider>) -> Result<bool> {
+        let mut result = true;
+        if let Some(targets) = &asset.targets {
+            let mut matched = false;
+            for name in targets {
+                let target = parse_target(name).context("Invalid asset targets")?;
+                matched |= &target == provider.get_target();
+            }
+            result &= matched;
+        }
+        if let Some(profiles) = &asset.profiles {
+            for name in profiles {
+                if name != "debug" && name != "release" {
+                    bail!("Invalid asset profiles: unknown profile '{}'", name);
+                }
+            }
+            result &= profiles.iter().any(|value| value == provider.get_profile());
+        }
+        Ok(result)
+    }
+
+    fn parse_source(
+        source: String,
+        provider: &Rc<dyn ProjectProvider>,
+        module_prop: &ModuleProp,
+    ) -> Result<PathBuf> {
         if source.is_empty() {
             bail!("Invalid source: value is empty");
         }
 
+        let source = Self::expand_placeholders(&source, true, provider, module_prop)
+            .context("Invalid source")?;
         let source_path = Path::new(&source);
         Self::check_path(source_path, "source")?;
 
@@ -171,18 +782,99 @@ impl Asset {
         Ok(result)
     }
 
-    fn parse_dest(dest: String, provider: &Rc<dyn ProjectProvider>) -> Result<PathBuf> {
+    /// Parses a path relative to the `OUT_DIR` of the crate build script.
+    fn parse_out_dir(
+        out_dir: String,
+        provider: &Rc<dyn ProjectProvider>,
+        module_prop: &ModuleProp,
+    ) -> Result<PathBuf> {
+        if out_dir.is_empty() {
+            bail!("Invalid out_dir: value is empty");
+        }
+
+        let out_dir = Self::expand_placeholders(&out_dir, true, provider, module_prop)
+            .context("Invalid out_dir")?;
+        let out_dir_path = PathBuf::from(out_dir);
+        Self::check_path(&out_dir_path, "out_dir")?;
+        Ok(out_dir_path)
+    }
+
+    fn parse_dest(
+        dest: String,
+        provider: &Rc<dyn ProjectProvider>,
+        module_prop: &ModuleProp,
+    ) -> Result<PathBuf> {
         if dest.is_empty() {
             bail!("Invalid dest: value is empty");
         }
 
+        let dest = Self::expand_placeholders(&dest, false, provider, module_prop)
+            .context("Invalid dest")?;
         let dest_path = Path::new(&dest);
         Self::check_path(dest_path, "dest")?;
+        let dest_path = Self::map_partition(dest_path, provider.get_manager())?;
+
+        let mut staging_path = provider.get_staging_path()?;
+        staging_path.push(dest_path);
+        Ok(staging_path)
+    }
 
-        let mut target_path = provider.get_target_path()?;
-        target_path.push("magisk");
-        target_path.push(dest_path);
-        Ok(target_path)
+    /// Replaces `{name}` placeholders with values of the current build.
+    /// With `glob` set, `{a,b}` alternations are left for the glob matcher.
+    fn expand_placeholders(
+        value: &str,
+        glob: bool,
+        provider: &Rc<dyn ProjectProvider>,
+        module_prop: &ModuleProp,
+    ) -> Result<String> {
+        let re = Regex::new(r"\{([^{}]*)\}")?;
+        let mut result = String::with_capacity(value.len());
+        let mut last = 0;
+        for caps in re.captures_iter(value) {
+            let all = caps.get(0).context("Invalid placeholder")?;
+            if glob && caps[1].contains(',') {
+                continue;
+            }
+            let replacement = match &caps[1] {
+                "target" => provider.get_target().to_string(),
+                "abi" => provider.get_target().abi().to_string(),
+                "libdir" => provider.get_target().libdir().to_string(),
+                "profile" => provider.get_profile().to_string(),
+                "id" => module_prop.id.clone(),
+                "version" => module_prop.version.clone(),
+                name => bail!("unknown placeholder '{{{}}}'", name),
+            };
+            result.push_str(&value[last..all.start()]);
+            result.push_str(&replacement);
+            last = all.end();
+        }
+        result.push_str(&value[last..]);
+        Ok(result)
+    }
+
+    /// Moves partition roots to where `manager` overlays them. Magisk only
+    /// mounts `system/`, so `vendor/...` becomes `system/vendor/...`.
+    fn map_partition(dest_path: &Path, manager: &Manager) -> Result<PathBuf> {
+        let Some(Component::Normal(root)) = dest_path.components().next() else {
+            return Ok(dest_path.to_path_buf());
+        };
+        let root = root.to_string_lossy();
+
+        if Self::UNSUPPORTED_ROOTS.contains(&root.as_ref()) {
+            bail!("Invalid dest: '/{}' can not be overlaid by a module", root);
+        }
+
+        let result = match (manager, root.as_ref()) {
+            (Manager::Magisk, "vendor" | "product" | "system_ext") => {
+                Path::new("system").join(dest_path)
+            }
+            (Manager::Magisk, "odm") => Path::new("system/vendor").join(dest_path),
+            (Manager::Magisk, "oem") => {
+                bail!("Invalid dest: '/oem' can not be overlaid by Magisk")
+            }
+            _ => dest_path.to_path_buf(),
+        };
+        Ok(result)
     }
 
     fn check_path(path: &Path, label: &str) -> Result<()> {
@@ -203,3 +895,65 @@ impl Asset {
         Ok(())
     }
 }
+
+#[derive(Debug, PartialEq)]
+pub enum AssetSource {
+    /// File, directory or glob path in the project or target dir.
+    Path(PathBuf),
+    /// Inline file content from `content = "..."`.
+    Content(String),
+    /// Path relative to the `OUT_DIR` of the crate build script, known only
+    /// after the build.
+    OutDir(PathBuf),
+    /// Output of a command run after the build.
+    Generate(Generator),
+}
+
+/// How symlinks found inside directory and glob sources are staged.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Symlinks {
+    /// Recreate the link itself, keeping its target as written.
+    Preserve,
+    /// Copy the file or directory the link points to.
+    Follow,
+}
+
+impl TryFrom<&str> for Symlinks {
+    type Error = anyhow::Error;
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        match name {
+            "preserve" => Ok(Symlinks::Preserve),
+            "follow" => Ok(Symlinks::Follow),
+            _ => bail!("Invalid symlinks mode: {}", name),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct WebUi {
+    pub source: PathBuf,
+    pub build: Option<Vec<String>>,
+}
+
+impl WebUi {
+    pub const DEST: &str = "webroot";
+    pub const INDEX: &str = "index.html";
+    pub const SHIM: &str = "module.js";
+
+    pub fn try_new(
+        source: String,
+        build: Option<Vec<String>>,
+        provider: &Rc<dyn ProjectProvider>,
+        module_prop: &ModuleProp,
+    ) -> Result<Self> {
+        let source = Asset::parse_source(source, provider, module_prop)
+            .context("Failed to initialize WebUi")?;
+        if let Some(value) = &build
+            && value.is_empty()
+        {
+            bail!("Invalid webui build: command is empty");
+        }
+        Ok(Self { source, build })
+    }
+}
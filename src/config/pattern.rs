@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod test;
+
+use anyhow::{Context, Result, bail};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::path::{Path, PathBuf};
+
+pub const IGNORE_FILE: &str = ".magiskignore";
+
+/// Splits a glob path into the directory to search and the pattern
+/// relative to it. Returns `None` for plain paths.
+pub fn split_glob(path: &Path) -> Option<(PathBuf, String)> {
+    let mut base = PathBuf::new();
+    let mut pattern: Vec<String> = vec![];
+    for comp in path.components() {
+        let part = comp.as_os_str().to_string_lossy();
+        if pattern.is_empty() && !part.contains(['*', '?', '[', '{']) {
+            base.push(comp);
+        } else {
+            pattern.push(part.into_owned());
+        }
+    }
+    match pattern.is_empty() {
+        true => None,
+        false => Some((base, pattern.join("/"))),
+    }
+}
+
+/// Compiles exclude patterns. A pattern without `/` matches a file or
+/// directory name at any depth, like in `.gitignore`.
+pub fn compile(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let trimmed = pattern.trim_start_matches('/');
+        let trimmed = trimmed.strip_suffix('/').unwrap_or(trimmed);
+        if trimmed.is_empty() {
+            bail!("Invalid pattern: '{}'", pattern);
+        }
+
+        let anchored = if pattern.trim_end_matches('/').contains('/') {
+            trimmed.to_string()
+        } else {
+            format!("**/{}", trimmed)
+        };
+        for glob in [anchored.clone(), format!("{}/**", anchored)] {
+            let glob = GlobBuilder::new(&glob)
+                .literal_separator(true)
+                .build()
+                .with_context(|| format!("Invalid pattern: '{}'", pattern))?;
+            builder.add(glob);
+        }
+    }
+    Ok(builder.build()?)
+}
+
+/// Reads the patterns of an ignore file, skipping blank lines and comments.
+pub fn parse_ignore(content: &str) -> Result<Vec<String>> {
+    let mut result: Vec<String> = vec![];
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('!') {
+            bail!(
+                "Invalid {}: negated patterns are not supported",
+                IGNORE_FILE
+            );
+        }
+        result.push(line.to_string());
+    }
+    Ok(result)
+}
@@ -0,0 +1,40 @@
+use super::*;
+use rstest::rstest;
+
+#[rstest]
+#[case("*.bak", "a.bak", true)]
+#[case("*.bak", "etc/deep/a.bak", true)]
+#[case("*.bak", "a.bak.conf", false)]
+#[case("tmp/", "etc/tmp/a.conf", true)]
+#[case("etc/*.conf", "etc/a.conf", true)]
+#[case("etc/*.conf", "etc/deep/a.conf", false)]
+#[case("etc/*.conf", "other/etc/a.conf", false)]
+#[case("/README.md", "README.md", true)]
+#[case("/README.md", "docs/README.md", false)]
+fn pattern_compile(#[case] pattern: &str, #[case] path: &str, #[case] expected: bool) {
+    let matcher = compile(&[pattern.to_string()]).unwrap();
+    assert_eq!(
+        matcher.is_match(path),
+        expected,
+        "pattern: '{}', path: '{}'",
+        pattern,
+        path
+    );
+}
+
+#[rstest]
+fn pattern_compile_err(#[values("", "/", "a[", "etc/{a,b")] pattern: &str) {
+    let result = compile(&[pattern.to_string()]);
+    assert!(
+        result.is_err(),
+        "result.is_err() not true. pattern: '{}'",
+        pattern
+    );
+}
+
+#[rstest]
+fn pattern_parse_ignore() {
+    let patterns = parse_ignore("# comment\n\n*.bak\n  target/tmp/  \n").unwrap();
+    assert_eq!(patterns, ["*.bak", "target/tmp/"]);
+    assert!(parse_ignore("!keep.conf\n").is_err());
+}
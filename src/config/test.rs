@@ -1,15 +1,17 @@
 use super::*;
-use crate::project::Target;
-use anyhow::Result;
+use crate::project::test_support::MockProject;
+use crate::project::{Manager, Target};
 use rstest::{fixture, rstest};
+use std::fs;
 
 #[rstest]
 fn asset_parse_source(
     #[values("target/cargo-magisk", "assets/customize.sh")] source: String,
     project_provider: Rc<dyn ProjectProvider>,
+    module_prop: ModuleProp,
 ) {
     let expected = get_expected_parse_source(&source);
-    let source_path = Asset::parse_source(source, &project_provider).unwrap();
+    let source_path = Asset::parse_source(source, &project_provider, &module_prop).unwrap();
     assert_eq!(source_path, expected);
 }
 
@@ -28,9 +30,10 @@ fn get_expected_parse_source(source: &str) -> PathBuf {
 fn asset_parse_dest(
     #[values("system/bin/cargo-magisk", "customize.sh")] dest: String,
     project_provider: Rc<dyn ProjectProvider>,
+    module_prop: ModuleProp,
 ) {
     let expected = get_expected_parse_dest(&dest);
-    let dest_path = Asset::parse_dest(dest, &project_provider).unwrap();
+    let dest_path = Asset::parse_dest(dest, &project_provider, &module_prop).unwrap();
     assert_eq!(dest_path, expected);
 }
 
@@ -47,6 +50,50 @@ fn get_expected_parse_dest(dest: &str) -> PathBuf {
     PathBuf::from(expected_str)
 }
 
+#[rstest]
+#[case(
+    Manager::Magisk,
+    "vendor/lib64/libfoo.so",
+    "system/vendor/lib64/libfoo.so"
+)]
+#[case(Manager::Magisk, "product/etc/foo.xml", "system/product/etc/foo.xml")]
+#[case(
+    Manager::Magisk,
+    "system_ext/etc/foo.xml",
+    "system/system_ext/etc/foo.xml"
+)]
+#[case(Manager::Magisk, "odm/etc/foo.xml", "system/vendor/odm/etc/foo.xml")]
+#[case(Manager::Magisk, "system/bin/foo", "system/bin/foo")]
+#[case(Manager::Magisk, "vendor", "system/vendor")]
+#[case(Manager::Magisk, "system_ext", "system/system_ext")]
+#[case(Manager::Magisk, "odm", "system/vendor/odm")]
+#[case(Manager::KernelSu, "vendor", "vendor")]
+#[case(Manager::KernelSu, "vendor/lib64/libfoo.so", "vendor/lib64/libfoo.so")]
+#[case(Manager::KernelSu, "oem/etc/foo.xml", "oem/etc/foo.xml")]
+#[case(Manager::KernelSu, "webroot/index.html", "webroot/index.html")]
+fn asset_map_partition(#[case] manager: Manager, #[case] dest: &str, #[case] expected: &str) {
+    let dest_path = Asset::map_partition(Path::new(dest), &manager).unwrap();
+    assert_eq!(dest_path, PathBuf::from(expected));
+}
+
+#[rstest]
+fn asset_map_partition_err(
+    #[values(Manager::Magisk, Manager::KernelSu)] manager: Manager,
+    #[values("data/local/tmp/foo", "apex/com.android.foo/lib", "data", "apex")] dest: &str,
+) {
+    let dest_path = Asset::map_partition(Path::new(dest), &manager);
+    assert!(
+        dest_path.is_err(),
+        "dest_path.is_err() not true. dest: '{}'",
+        dest
+    );
+}
+
+#[rstest]
+fn asset_map_partition_magisk_err(#[values("oem", "oem/etc/foo.xml")] dest: &str) {
+    assert!(Asset::map_partition(Path::new(dest), &Manager::Magisk).is_err());
+}
+
 #[rstest]
 fn asset_parse_source_err(
     #[values(
@@ -61,8 +108,9 @@ fn asset_parse_source_err(
     )]
     source: String,
     project_provider: Rc<dyn ProjectProvider>,
+    module_prop: ModuleProp,
 ) {
-    let source_path = Asset::parse_source(source.clone(), &project_provider);
+    let source_path = Asset::parse_source(source.clone(), &project_provider, &module_prop);
     assert!(
         source_path.is_err(),
         "source_path.is_err() not true. source: '{}'",
@@ -84,8 +132,9 @@ fn asset_parse_dest_err(
     )]
     dest: String,
     project_provider: Rc<dyn ProjectProvider>,
+    module_prop: ModuleProp,
 ) {
-    let dest_path = Asset::parse_dest(dest.clone(), &project_provider);
+    let dest_path = Asset::parse_dest(dest.clone(), &project_provider, &module_prop);
     assert!(
         dest_path.is_err(),
         "dest_path.is_err() not true. dest: '{}'",
@@ -129,29 +178,646 @@ fn module_prop_validate_err(
     );
 }
 
-#[fixture]
-fn project_provider() -> Rc<dyn ProjectProvider> {
-    Rc::new(MockProject {})
+#[rstest]
+#[case(None, "")]
+#[case(Some("A module"), "description=A module\n")]
+fn module_prop_display(#[case] description: Option<&str>, #[case] expected_tail: &str) {
+    let module_prop = ModuleProp::new(
+        "a_module".to_string(),
+        "name".to_string(),
+        "1.0.0".to_string(),
+        "author".to_string(),
+        description.map(str::to_string),
+    )
+    .unwrap();
+    let expected = format!(
+        "id=a_module\nname=name\nauthor=author\nversion=1.0.0\nversionCode=010000900\n{}",
+        expected_tail
+    );
+    assert_eq!(module_prop.to_string(), expected);
 }
 
-#[derive(Debug)]
-struct MockProject;
-impl ProjectProvider for MockProject {
-    fn get_project_path(&self) -> Result<PathBuf> {
-        Ok(PathBuf::from("/workspace"))
-    }
+#[rstest]
+fn module_prop_to_js() {
+    let module_prop = ModuleProp::new(
+        "a_module".to_string(),
+        "A \"quoted\" name".to_string(),
+        "1.2.3".to_string(),
+        "author".to_string(),
+        None,
+    )
+    .unwrap();
+    let expected = "window.MODULE = Object.freeze({\"author\":\"author\",\"id\":\"a_module\",\
+                    \"name\":\"A \\\"quoted\\\" name\",\"version\":\"1.2.3\",\
+                    \"versionCode\":10203900});\n";
+    assert_eq!(module_prop.to_js(), expected);
+}
 
-    fn get_target_path(&self) -> Result<PathBuf> {
-        let mut project_path = self.get_project_path().unwrap();
-        project_path.push("target/arch/build_type");
-        Ok(project_path)
-    }
+#[rstest]
+fn webui_try_new(project_provider: Rc<dyn ProjectProvider>, module_prop: ModuleProp) {
+    let webui = WebUi::try_new(
+        "target/webui".to_string(),
+        Some(vec![
+            "npm".to_string(),
+            "run".to_string(),
+            "build".to_string(),
+        ]),
+        &project_provider,
+        &module_prop,
+    )
+    .unwrap();
+    assert_eq!(
+        webui.source,
+        PathBuf::from("/workspace/target/arch/build_type/webui")
+    );
+}
+
+#[rstest]
+fn webui_try_new_err(
+    #[values(("", None), ("../webui", None), ("webui", Some(vec![])))] input: (
+        &str,
+        Option<Vec<String>>,
+    ),
+    project_provider: Rc<dyn ProjectProvider>,
+    module_prop: ModuleProp,
+) {
+    let (source, build) = input;
+    let result = WebUi::try_new(source.to_string(), build, &project_provider, &module_prop);
+    assert!(
+        result.is_err(),
+        "result.is_err() not true. source: '{}'",
+        source
+    );
+}
+
+#[rstest]
+#[case(
+    "target/{target}/daemon",
+    "/workspace/target/arch/build_type/aarch64-linux-android/daemon"
+)]
+#[case("prebuilt/{abi}/libfoo.so", "/workspace/prebuilt/arm64-v8a/libfoo.so")]
+#[case(
+    "dist/{id}-{version}-{profile}.zip",
+    "/workspace/dist/a_module-1.2.3-debug.zip"
+)]
+#[case(
+    "assets/{abi}/*.{conf,txt}",
+    "/workspace/assets/arm64-v8a/*.{conf,txt}"
+)]
+fn asset_parse_source_placeholders(
+    #[case] source: &str,
+    #[case] expected: &str,
+    project_provider: Rc<dyn ProjectProvider>,
+    module_prop: ModuleProp,
+) {
+    let source_path =
+        Asset::parse_source(source.to_string(), &project_provider, &module_prop).unwrap();
+    assert_eq!(source_path, PathBuf::from(expected));
+}
+
+#[rstest]
+fn asset_parse_dest_placeholders(
+    project_provider: Rc<dyn ProjectProvider>,
+    module_prop: ModuleProp,
+) {
+    let dest_path = Asset::parse_dest(
+        "system/{libdir}/libfoo.so".to_string(),
+        &project_provider,
+        &module_prop,
+    )
+    .unwrap();
+    let expected = "/workspace/target/arch/build_type/magisk/system/lib64/libfoo.so";
+    assert_eq!(dest_path, PathBuf::from(expected));
+}
+
+#[rstest]
+fn asset_parse_placeholders_err(
+    #[values(
+        "{arch}/libfoo.so",
+        "{}/libfoo.so",
+        "{id/libfoo.so}",
+        "{abi}/../libfoo.so"
+    )]
+    value: &str,
+    project_provider: Rc<dyn ProjectProvider>,
+    module_prop: ModuleProp,
+) {
+    let source_path = Asset::parse_source(value.to_string(), &project_provider, &module_prop);
+    assert!(
+        source_path.is_err(),
+        "source_path.is_err() not true. value: '{}'",
+        value
+    );
+    let dest_path = Asset::parse_dest(value.to_string(), &project_provider, &module_prop);
+    assert!(
+        dest_path.is_err(),
+        "dest_path.is_err() not true. value: '{}'",
+        value
+    );
+}
+
+#[rstest]
+fn asset_parse_dest_alternation_err(
+    project_provider: Rc<dyn ProjectProvider>,
+    module_prop: ModuleProp,
+) {
+    let dest_path = Asset::parse_dest(
+        "system/etc/{a,b}.conf".to_string(),
+        &project_provider,
+        &module_prop,
+    );
+    assert!(dest_path.is_err());
+}
+
+#[rstest]
+#[case("assets/etc", false, false)]
+#[case("assets/etc/", false, true)]
+#[case("assets/etc", true, true)]
+fn asset_try_new_contents(
+    #[case] source: &str,
+    #[case] contents: bool,
+    #[case] expected: bool,
+    project_provider: Rc<dyn ProjectProvider>,
+    module_prop: ModuleProp,
+) {
+    let asset = Asset::try_new(
+        toml_asset(source, contents, None),
+        &project_provider,
+        &module_prop,
+    )
+    .unwrap();
+    assert_eq!(
+        asset.source,
+        AssetSource::Path(PathBuf::from("/workspace/assets/etc"))
+    );
+    assert_eq!(asset.contents, expected);
+    assert_eq!(asset.symlinks, Symlinks::Preserve);
+}
+
+#[rstest]
+#[case(Some("follow"), Some(Symlinks::Follow))]
+#[case(Some("preserve"), Some(Symlinks::Preserve))]
+#[case(Some("copy"), None)]
+fn asset_try_new_symlinks(
+    #[case] symlinks: Option<&str>,
+    #[case] expected: Option<Symlinks>,
+    project_provider: Rc<dyn ProjectProvider>,
+    module_prop: ModuleProp,
+) {
+    let asset = Asset::try_new(
+        toml_asset("assets/etc", false, symlinks),
+        &project_provider,
+        &module_prop,
+    );
+    assert_eq!(asset.ok().map(|value| value.symlinks), expected);
+}
+
+#[rstest]
+fn asset_try_new_content(project_provider: Rc<dyn ProjectProvider>, module_prop: ModuleProp) {
+    let mut value = toml_asset("assets/etc", false, None);
+    value.source = None;
+    value.content = Some("key={{ id }}\n".to_string());
+    value.template = true;
+    let asset = Asset::try_new(value, &project_provider, &module_prop).unwrap();
+    assert_eq!(
+        asset.source,
+        AssetSource::Content("key={{ id }}\n".to_string())
+    );
+    assert!(asset.template);
+}
+
+#[rstest]
+#[case(Some("assets/etc"), Some("text"), false)]
+#[case(None, None, false)]
+#[case(Some("assets/etc/*.conf"), None, true)]
+fn asset_try_new_err(
+    #[case] source: Option<&str>,
+    #[case] content: Option<&str>,
+    #[case] template: bool,
+    project_provider: Rc<dyn ProjectProvider>,
+    module_prop: ModuleProp,
+) {
+    let mut value = toml_asset("assets/etc", false, None);
+    value.source = source.map(|value| toml_types::Source::Path(value.to_string()));
+    value.content = content.map(str::to_string);
+    value.template = template;
+    let asset = Asset::try_new(value, &project_provider, &module_prop);
+    assert!(asset.is_err());
+}
+
+#[rstest]
+#[case(None, None, true)]
+#[case(Some(vec!["aarch64-linux-android"]), None, true)]
+#[case(Some(vec!["x86", "arm64-v8a"]), None, true)]
+#[case(Some(vec!["x86_64-linux-android"]), None, false)]
+#[case(Some(vec![]), None, false)]
+#[case(None, Some(vec!["debug"]), true)]
+#[case(None, Some(vec!["release"]), false)]
+#[case(Some(vec!["aarch64-linux-android"]), Some(vec!["release"]), false)]
+fn asset_is_enabled(
+    #[case] targets: Option<Vec<&str>>,
+    #[case] profiles: Option<Vec<&str>>,
+    #[case] expected: bool,
+    project_provider: Rc<dyn ProjectProvider>,
+) {
+    let mut value = toml_asset("assets/etc", false, None);
+    value.targets = targets.map(|value| value.into_iter().map(str::to_string).collect());
+    value.profiles = profiles.map(|value| value.into_iter().map(str::to_string).collect());
+    let enabled = Asset::is_enabled(&value, &project_provider).unwrap();
+    assert_eq!(enabled, expected);
+}
+
+#[rstest]
+#[case(Some(vec!["mips-linux-android"]), None)]
+#[case(None, Some(vec!["bench"]))]
+fn asset_is_enabled_err(
+    #[case] targets: Option<Vec<&str>>,
+    #[case] profiles: Option<Vec<&str>>,
+    project_provider: Rc<dyn ProjectProvider>,
+) {
+    let mut value = toml_asset("assets/etc", false, None);
+    value.targets = targets.map(|value| value.into_iter().map(str::to_string).collect());
+    value.profiles = profiles.map(|value| value.into_iter().map(str::to_string).collect());
+    assert!(Asset::is_enabled(&value, &project_provider).is_err());
+}
+
+#[rstest]
+#[case(Target::Arm64V8a, "name arm64", "author", 2)]
+#[case(Target::X86, "name x86", "x86 author", 1)]
+#[case(Target::X86_64, "name", "author", 1)]
+fn config_apply_target_override(
+    #[case] target: Target,
+    #[case] name: &str,
+    #[case] author: &str,
+    #[case] assets: usize,
+) {
+    let mut magisk: toml_types::Magisk = toml::from_str(TARGET_OVERRIDE_TOML).unwrap();
+    Config::apply_target_override(&mut magisk, &target).unwrap();
+    assert_eq!(magisk.name.as_deref(), Some(name));
+    assert_eq!(magisk.author.as_deref(), Some(author));
+    assert_eq!(magisk.assets.len(), assets);
+    assert!(magisk.target.is_empty());
+}
+
+#[rstest]
+#[case("[target.mips-linux-android]\nname = \"mips\"\n")]
+#[case("[target.arm64-v8a]\nname = \"a\"\n[target.aarch64-linux-android]\nname = \"b\"\n")]
+fn config_apply_target_override_err(#[case] table: &str) {
+    let content = format!(
+        "id = \"a_module\"\nname = \"name\"\nauthor = \"author\"\nassets = []\n{}",
+        table
+    );
+    let mut magisk: toml_types::Magisk = toml::from_str(&content).unwrap();
+    let result = Config::apply_target_override(&mut magisk, &Target::Arm64V8a);
+    assert!(result.is_err());
+}
+
+const TARGET_OVERRIDE_TOML: &str = r#"
+id = "a_module"
+name = "name"
+author = "author"
+assets = [{ source = "target/cargo-magisk", dest = "system/bin/cargo-magisk" }]
+
+[target.aarch64-linux-android]
+name = "name arm64"
+assets = [{ source = "assets/arm64.conf", dest = "system/etc/arm64.conf" }]
+
+[target.x86]
+name = "name x86"
+author = "x86 author"
+"#;
+
+#[rstest]
+#[case(None, "a_module", "name", 1, &[])]
+#[case(Some("lite"), "a_module", "name lite", 0, &["lite"])]
+#[case(Some("debug"), "a_module.debug", "name", 1, &[])]
+fn config_apply_variant(
+    #[case] variant: Option<&str>,
+    #[case] id: &str,
+    #[case] name: &str,
+    #[case] assets: usize,
+    #[case] features: &[&str],
+) {
+    let mut magisk: toml_types::Magisk = toml::from_str(VARIANTS_TOML).unwrap();
+    let result = Config::apply_variant(&mut magisk, variant).unwrap();
+    assert_eq!(magisk.id, id);
+    assert_eq!(magisk.name.as_deref(), Some(name));
+    assert_eq!(magisk.assets.len(), assets);
+    assert_eq!(result, features);
+    assert!(magisk.variants.is_empty());
+}
+
+#[rstest]
+#[case(Some("full"), "")]
+#[case(None, "[variants.\"a b\"]\n")]
+#[case(Some("lite"), "[variants.lite]\nfeatures = [\"a,b\"]\n")]
+fn config_apply_variant_err(#[case] variant: Option<&str>, #[case] table: &str) {
+    let content = format!(
+        "id = \"a_module\"\nname = \"name\"\nauthor = \"author\"\n{}",
+        table
+    );
+    let mut magisk: toml_types::Magisk = toml::from_str(&content).unwrap();
+    let result = Config::apply_variant(&mut magisk, variant);
+    assert!(result.is_err());
+}
+
+const VARIANTS_TOML: &str = r#"
+id = "a_module"
+name = "name"
+author = "author"
+assets = [{ source = "target/cargo-magisk", dest = "system/bin/cargo-magisk" }]
+props = { updateJson = "https://example.com/update.json" }
+
+[variants.lite]
+name = "name lite"
+assets = []
+features = ["lite"]
+
+[variants.debug]
+id = "a_module.debug"
+props = { updateJson = "https://example.com/debug.json" }
+"#;
+
+#[rstest]
+fn module_prop_with_props(module_prop: ModuleProp) {
+    let props = BTreeMap::from([("updateJson".to_string(), "https://a/b.json".to_string())]);
+    let module_prop = module_prop.with_props(props).unwrap();
+    assert!(
+        module_prop
+            .to_string()
+            .ends_with("\nupdateJson=https://a/b.json\n")
+    );
+}
+
+#[rstest]
+fn module_prop_with_props_err(
+    #[values(("id", "b"), ("1key", "b"), ("key", "a\nb"))] prop: (&str, &str),
+    module_prop: ModuleProp,
+) {
+    let props = BTreeMap::from([(prop.0.to_string(), prop.1.to_string())]);
+    assert!(module_prop.with_props(props).is_err());
+}
+
+#[rstest]
+#[case(
+    r#"source = { out_dir = "generated/{abi}.conf" }"#,
+    "generated/arm64-v8a.conf",
+    false
+)]
+#[case(r#"source = { out_dir = "generated/" }"#, "generated", true)]
+fn asset_try_new_out_dir(
+    #[case] source: &str,
+    #[case] expected: &str,
+    #[case] contents: bool,
+    project_provider: Rc<dyn ProjectProvider>,
+    module_prop: ModuleProp,
+) {
+    let content = format!("{}\ndest = \"system/etc\"\n", source);
+    let value: toml_types::Asset = toml::from_str(&content).unwrap();
+    let asset = Asset::try_new(value, &project_provider, &module_prop).unwrap();
+    assert_eq!(asset.source, AssetSource::OutDir(PathBuf::from(expected)));
+    assert_eq!(asset.contents, contents);
+}
+
+#[rstest]
+fn asset_try_new_out_dir_err(
+    #[values(
+        r#"{ out_dir = "../foo.conf" }"#,
+        r#"{ out_dir = "" }"#,
+        r#"{ path = "a" }"#
+    )]
+    source: &str,
+    project_provider: Rc<dyn ProjectProvider>,
+    module_prop: ModuleProp,
+) {
+    let content = format!("source = {}\ndest = \"system/etc\"\n", source);
+    let result = toml::from_str::<toml_types::Asset>(&content)
+        .map_err(anyhow::Error::from)
+        .and_then(|value| Asset::try_new(value, &project_provider, &module_prop));
+    assert!(
+        result.is_err(),
+        "result.is_err() not true. source: '{}'",
+        source
+    );
+}
+
+#[rstest]
+fn asset_try_new_generate(project_provider: Rc<dyn ProjectProvider>, module_prop: ModuleProp) {
+    let content = r#"
+dest = "system/etc/hosts"
+generate = { command = ["./gen-hosts.sh", "{abi}"], output = "target/hosts", inputs = ["lists/"] }
+"#;
+    let value: toml_types::Asset = toml::from_str(content).unwrap();
+    let asset = Asset::try_new(value, &project_provider, &module_prop).unwrap();
+    let expected = Generator {
+        command: vec!["./gen-hosts.sh".to_string(), "{abi}".to_string()],
+        output: PathBuf::from("/workspace/target/arch/build_type/hosts"),
+        inputs: Some(vec![PathBuf::from("/workspace/lists")]),
+    };
+    assert_eq!(asset.source, AssetSource::Generate(expected));
+}
+
+#[rstest]
+fn asset_try_new_generate_err(
+    #[values(
+        r#"generate = { command = [], output = "target/hosts" }"#,
+        r#"generate = { command = [""], output = "target/hosts" }"#,
+        r#"generate = { command = ["gen"], output = "../hosts" }"#,
+        r#"generate = { command = ["gen"], output = "target/hosts", inputs = ["/etc"] }"#,
+        "generate = { command = [\"gen\"], output = \"target/hosts\" }\nsource = \"hosts\""
+    )]
+    generate: &str,
+    project_provider: Rc<dyn ProjectProvider>,
+    module_prop: ModuleProp,
+) {
+    let content = format!("dest = \"system/etc/hosts\"\n{}\n", generate);
+    let value: toml_types::Asset = toml::from_str(&content).unwrap();
+    let result = Asset::try_new(value, &project_provider, &module_prop);
+    assert!(
+        result.is_err(),
+        "result.is_err() not true. generate: '{}'",
+        generate
+    );
+}
 
-    fn get_target(&self) -> &Target {
-        &Target::Arm64V8a
+#[rstest]
+fn config_parse_hooks() {
+    let content = r#"
+pre_build = ["./version.sh > build-info.txt"]
+post_package = [["./sign.sh", "--key", "release.pem"], "cp -r target out"]
+"#;
+    let hooks = Config::parse_hooks(toml::from_str(content).unwrap()).unwrap();
+    assert_eq!(
+        hooks.get(HookStage::PreBuild),
+        [vec!["sh", "-c", "./version.sh > build-info.txt"]]
+    );
+    assert!(hooks.get(HookStage::PostBuild).is_empty());
+    assert!(hooks.get(HookStage::PrePackage).is_empty());
+    assert_eq!(
+        hooks.get(HookStage::PostPackage),
+        [
+            vec!["./sign.sh", "--key", "release.pem"],
+            vec!["sh", "-c", "cp -r target out"]
+        ]
+    );
+}
+
+#[rstest]
+fn config_parse_hooks_err(#[values("post_build = [[]]", r#"pre_package = [[""]]"#)] content: &str) {
+    let hooks = Config::parse_hooks(toml::from_str(content).unwrap());
+    assert!(
+        hooks.is_err(),
+        "hooks.is_err() not true. content: '{}'",
+        content
+    );
+}
+
+#[rstest]
+#[case(false, true, false, None, 0)]
+#[case(true, false, false, Some("Magisk.toml"), 0)]
+#[case(true, true, false, Some("Magisk.toml"), 1)]
+#[case(false, true, true, Some("custom.toml"), 1)]
+#[case(true, true, true, Some("custom.toml"), 2)]
+#[case(true, false, true, Some("custom.toml"), 1)]
+fn config_select_config(
+    #[case] has_default: bool,
+    #[case] has_metadata: bool,
+    #[case] has_custom: bool,
+    #[case] expected: Option<&str>,
+    #[case] warnings: usize,
+) {
+    let project = tempfile::tempdir().unwrap();
+    let root = project.path();
+    let default_path = root.join(CONFIG_FILE);
+    if has_default {
+        fs::write(&default_path, "").unwrap();
+    }
+    let config_path = has_custom.then(|| root.join("custom.toml"));
+    if let Some(path) = &config_path {
+        fs::write(path, "").unwrap();
     }
 
-    fn is_release(&self) -> bool {
-        false
+    let (selected, result) =
+        Config::select_config(config_path, &default_path, has_metadata).unwrap();
+    assert_eq!(selected, expected.map(|value| root.join(value)));
+    assert_eq!(result.len(), warnings, "{:?}", result);
+}
+
+#[rstest]
+fn config_select_config_same_file() {
+    let project = tempfile::tempdir().unwrap();
+    let default_path = project.path().join(CONFIG_FILE);
+    fs::write(&default_path, "").unwrap();
+    let (selected, warnings) =
+        Config::select_config(Some(default_path.clone()), &default_path, false).unwrap();
+    assert_eq!(selected, Some(default_path));
+    assert!(warnings.is_empty());
+}
+
+#[rstest]
+#[case(false, false)]
+#[case(false, true)]
+fn config_select_config_err(#[case] has_metadata: bool, #[case] missing_custom: bool) {
+    let project = tempfile::tempdir().unwrap();
+    let root = project.path();
+    let config_path = missing_custom.then(|| root.join("custom.toml"));
+    let result = Config::select_config(config_path, &root.join(CONFIG_FILE), has_metadata);
+    assert!(result.is_err());
+}
+
+#[rstest]
+fn config_load_magisk_toml(project_provider: Rc<dyn ProjectProvider>) {
+    let project = tempfile::tempdir().unwrap();
+    let root = project.path();
+    let manifest_path = root.join("Cargo.toml");
+    fs::write(
+        &manifest_path,
+        "[package]\nname = \"helper\"\nversion = \"1.2.3\"\n",
+    )
+    .unwrap();
+    fs::write(
+        root.join(CONFIG_FILE),
+        "id = \"standalone\"\nname = \"Standalone\"\nauthor = \"author\"\nassets = []\n",
+    )
+    .unwrap();
+
+    let package = PackageInfo {
+        name: "helper".to_string(),
+        version: "1.2.3".to_string(),
+        ..Default::default()
+    };
+    let config = Config::load_with_path(manifest_path, None, &project_provider, &package).unwrap();
+    assert_eq!(config.module_prop.id, "standalone");
+    assert_eq!(config.module_prop.version, "1.2.3");
+}
+
+#[rstest]
+#[case(None, "Team")]
+#[case(
+    Some("author = \"Maintainers\"\nrequires = { min_api = 30 }\n"),
+    "Maintainers"
+)]
+fn config_load_package_info(
+    #[case] defaults: Option<&str>,
+    #[case] author: &str,
+    project_provider: Rc<dyn ProjectProvider>,
+) {
+    let project = tempfile::tempdir().unwrap();
+    let manifest_path = project.path().join("Cargo.toml");
+    fs::write(
+        &manifest_path,
+        "[package]\nname = \"helper\"\nversion.workspace = true\n\n\
+         [package.metadata.magisk]\nid = \"helper_module\"\n",
+    )
+    .unwrap();
+    let package = PackageInfo {
+        name: "helper".to_string(),
+        version: "0.3.0".to_string(),
+        authors: vec!["Team".to_string()],
+        description: Some("A\n  helper".to_string()),
+        defaults: defaults.map(|value| toml::from_str(value).unwrap()),
+    };
+
+    let config = Config::load_with_path(manifest_path, None, &project_provider, &package).unwrap();
+    assert_eq!(config.module_prop.id, "helper_module");
+    assert_eq!(config.module_prop.name, "helper");
+    assert_eq!(config.module_prop.author, author);
+    assert_eq!(config.module_prop.version, "0.3.0");
+    assert_eq!(config.module_prop.description.as_deref(), Some("A helper"));
+    let min_api = defaults.is_some().then_some(30);
+    assert_eq!(config.requires.min_api, min_api);
+}
+
+fn toml_asset(source: &str, contents: bool, symlinks: Option<&str>) -> toml_types::Asset {
+    toml_types::Asset {
+        source: Some(toml_types::Source::Path(source.to_string())),
+        content: None,
+        generate: None,
+        dest: "system/etc".to_string(),
+        option: None,
+        exclude: vec![],
+        contents,
+        symlinks: symlinks.map(str::to_string),
+        overwrite: false,
+        template: false,
+        targets: None,
+        profiles: None,
     }
 }
+
+#[fixture]
+fn module_prop() -> ModuleProp {
+    ModuleProp::new(
+        "a_module".to_string(),
+        "name".to_string(),
+        "1.2.3".to_string(),
+        "author".to_string(),
+        None,
+    )
+    .unwrap()
+}
+
+#[fixture]
+fn project_provider() -> Rc<dyn ProjectProvider> {
+    Rc::new(MockProject::default())
+}
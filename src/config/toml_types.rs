@@ -1,31 +1,239 @@
 use serde::Deserialize;
+use std::collections::BTreeMap;
+use toml::Table;
 
 #[derive(Deserialize)]
 pub(super) struct Manifest {
     pub package: Package,
 }
 
+/// Only the metadata is read from the manifest, the other package fields
+/// come resolved from `cargo metadata`.
 #[derive(Deserialize)]
 pub(super) struct Package {
-    pub version: String,
-    pub metadata: Metadata,
+    #[serde(default)]
+    pub metadata: Option<Metadata>,
 }
 
-#[derive(Deserialize)]
+/// `magisk` is kept as a table, so workspace defaults can be merged in
+/// before it is read as [`Magisk`].
+#[derive(Deserialize, Clone)]
 pub(super) struct Metadata {
-    pub magisk: Magisk,
+    #[serde(default)]
+    pub magisk: Option<Table>,
 }
 
 #[derive(Deserialize)]
 pub(super) struct Magisk {
     pub id: String,
-    pub name: String,
-    pub author: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub assets: Vec<Asset>,
+    #[serde(default)]
+    pub webui: Option<WebUi>,
+    #[serde(default)]
+    pub scripts: Vec<Script>,
+    #[serde(default)]
+    pub services: Vec<Service>,
+    #[serde(default)]
+    pub bootloop_guard: Option<u32>,
+    #[serde(default)]
+    pub data_dir: Option<DataDir>,
+    #[serde(default)]
+    pub options: Vec<InstallOption>,
+    #[serde(default)]
+    pub requires: Option<Requires>,
+    #[serde(default)]
+    pub remove: Vec<String>,
+    #[serde(default)]
+    pub replace: Vec<String>,
+    #[serde(default)]
+    pub apps: Vec<App>,
+    #[serde(default)]
+    pub vars: BTreeMap<String, String>,
+    #[serde(default)]
+    pub props: BTreeMap<String, String>,
+    #[serde(default)]
+    pub target: BTreeMap<String, TargetOverride>,
+    #[serde(default)]
+    pub variants: BTreeMap<String, Variant>,
+    #[serde(default)]
+    pub hooks: Hooks,
+}
+
+#[derive(Deserialize, Default)]
+pub(super) struct Hooks {
+    #[serde(default)]
+    pub pre_build: Vec<HookCommand>,
+    #[serde(default)]
+    pub post_build: Vec<HookCommand>,
+    #[serde(default)]
+    pub pre_package: Vec<HookCommand>,
+    #[serde(default)]
+    pub post_package: Vec<HookCommand>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub(super) enum HookCommand {
+    Shell(String),
+    Args(Vec<String>),
+}
+
+#[derive(Deserialize)]
+pub(super) struct TargetOverride {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
     pub assets: Vec<Asset>,
 }
 
+#[derive(Deserialize)]
+pub(super) struct Variant {
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub assets: Option<Vec<Asset>>,
+    #[serde(default)]
+    pub features: Vec<String>,
+    #[serde(default)]
+    pub props: BTreeMap<String, String>,
+}
+
 #[derive(Deserialize)]
 pub(super) struct Asset {
-    pub source: String,
+    #[serde(default)]
+    pub source: Option<Source>,
+    #[serde(default)]
+    pub content: Option<String>,
+    #[serde(default)]
+    pub generate: Option<Generate>,
     pub dest: String,
+    #[serde(default)]
+    pub option: Option<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    #[serde(default)]
+    pub contents: bool,
+    #[serde(default)]
+    pub symlinks: Option<String>,
+    #[serde(default)]
+    pub overwrite: bool,
+    #[serde(default)]
+    pub template: bool,
+    #[serde(default)]
+    pub targets: Option<Vec<String>>,
+    #[serde(default)]
+    pub profiles: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct Generate {
+    pub command: Vec<String>,
+    pub output: String,
+    #[serde(default)]
+    pub inputs: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub(super) enum Source {
+    Path(String),
+    OutDir { out_dir: String },
+}
+
+#[derive(Deserialize)]
+pub(super) struct WebUi {
+    pub source: String,
+    #[serde(default)]
+    pub build: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct Script {
+    pub binary: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    pub stage: String,
+    #[serde(default)]
+    pub wait_for_boot: bool,
+    #[serde(default)]
+    pub log: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct Service {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub binary: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub restart: Option<String>,
+    #[serde(default)]
+    pub backoff: Option<u32>,
+    #[serde(default)]
+    pub max_backoff: Option<u32>,
+    #[serde(default)]
+    pub log: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct DataDir {
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub clean_on_uninstall: bool,
+}
+
+#[derive(Deserialize)]
+pub(super) struct InstallOption {
+    pub name: String,
+    pub prompt: String,
+    #[serde(default)]
+    pub default: bool,
+    #[serde(default)]
+    pub timeout: Option<u32>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct Requires {
+    #[serde(default)]
+    pub min_api: Option<u32>,
+    #[serde(default)]
+    pub min_magisk: Option<u32>,
+    #[serde(default)]
+    pub min_ksu: Option<u32>,
+    #[serde(default)]
+    pub abis: Option<Vec<String>>,
+    #[serde(default)]
+    pub modules: Vec<String>,
+    #[serde(default)]
+    pub conflicts: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub(super) struct App {
+    pub apk: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default, rename = "priv")]
+    pub privileged: bool,
+    #[serde(default)]
+    pub permissions: Option<Vec<String>>,
 }
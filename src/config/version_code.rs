@@ -5,7 +5,7 @@ use anyhow::{Context, bail};
 use regex::Regex;
 use std::fmt::Display;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub struct VersionCode([u8; 9]);
 
 impl VersionCode {
@@ -15,6 +15,12 @@ impl VersionCode {
     const PRE_TYPE_POS: usize = 6;
     const PRE_CODE_POS: usize = 7;
 
+    pub fn value(&self) -> u32 {
+        self.0
+            .iter()
+            .fold(0, |acc, &digit| acc * 10 + u32::from(digit))
+    }
+
     fn set_two_digits(value: u8, arr: &mut [u8; 9], start_arr: usize) {
         if value >= 10 {
             arr[start_arr] = value / 10;
@@ -35,3 +35,12 @@ fn version_code_from_str_err(#[case] version_str: &str) {
         version_str,
     )
 }
+
+#[rstest]
+#[case("1.0.0", 10000900)]
+#[case("2.5.10-beta.5", 20510205)]
+#[case("99.99.99-rc.99", 999999399)]
+fn version_code_value(#[case] version_str: &str, #[case] expected: u32) {
+    let version_code = VersionCode::try_from(version_str).unwrap();
+    assert_eq!(version_code.value(), expected);
+}
@@ -0,0 +1,20 @@
+#[cfg(test)]
+mod test;
+
+use toml::Table;
+
+/// Merges `config` over the `defaults` table. Nested tables are merged,
+/// other values in `config` replace the default.
+pub(super) fn merge(defaults: Table, config: Table) -> Table {
+    let mut result = defaults;
+    for (key, value) in config {
+        let value = match (result.remove(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(over)) => {
+                toml::Value::Table(merge(base, over))
+            }
+            (_, value) => value,
+        };
+        result.insert(key, value);
+    }
+    result
+}
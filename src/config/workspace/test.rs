@@ -0,0 +1,17 @@
+use super::*;
+use rstest::rstest;
+
+#[rstest]
+fn merge_tables() {
+    let defaults: Table = toml::from_str(
+        "author = \"Team\"\nassets = [1]\n[requires]\nmin_api = 29\nmodules = [\"a\"]\n",
+    )
+    .unwrap();
+    let config: Table =
+        toml::from_str("id = \"helper\"\nassets = [2]\n[requires]\nmin_api = 31\n").unwrap();
+    let expected: Table = toml::from_str(
+        "author = \"Team\"\nid = \"helper\"\nassets = [2]\n[requires]\nmin_api = 31\nmodules = [\"a\"]\n",
+    )
+    .unwrap();
+    assert_eq!(merge(defaults, config), expected);
+}
@@ -1,17 +1,42 @@
-use crate::config::Config;
-use crate::project::{ProjectProvider, Target};
-use anyhow::{Result, bail};
+mod generate;
+mod hook;
+mod message;
+mod resolve;
+#[cfg(test)]
+mod test;
+
+use crate::apk::ApkManifest;
+use crate::config::{
+    App, Asset, AssetSource, Config, DataDir, HookStage, InstallOption, ModuleProp, WebUi,
+};
+use crate::project::ProjectProvider;
+use crate::script::{self, ScriptSet};
+use anyhow::{Context, Result, bail};
+use resolve::{Origin, StagedFile, StagedSource};
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 use std::fs;
-use std::process::Command;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
 use std::rc::Rc;
+use walkdir::WalkDir;
+
+/// Directory in the target dir keeping the input state of generated assets.
+const GENERATE_STAMP_DIR: &str = "magisk-generate";
 
 pub trait Deploy: Debug {
     fn deploy(&self, config: &Config) -> Result<()>;
 }
 
 trait Build: Debug {
-    fn build(&self, target: &Target, release: bool, cargo_build: Option<String>) -> Result<()>;
+    /// Builds the crate with the given cargo features and returns the JSON
+    /// messages cargo printed.
+    fn build(
+        &self,
+        project: &dyn ProjectProvider,
+        cargo_build: Option<String>,
+        features: &[String],
+    ) -> Result<String>;
 }
 
 #[derive(Debug)]
@@ -32,63 +57,318 @@ impl DefaultDeploy {
     }
 
     fn clean(&self) -> Result<()> {
-        let mut project_path = self.project_provider.get_target_path()?;
-        project_path.push("magisk");
-
-        if project_path.exists() {
-            fs::remove_dir_all(project_path)?;
+        let staging_path = self.staging_path()?;
+        if staging_path.exists() {
+            fs::remove_dir_all(staging_path)?;
         }
         Ok(())
     }
+
+    fn staging_path(&self) -> Result<PathBuf> {
+        self.project_provider.get_staging_path()
+    }
 }
 
 impl Deploy for DefaultDeploy {
     fn deploy(&self, config: &Config) -> Result<()> {
         self.clean()?;
-        self.build.build(
-            self.project_provider.get_target(),
-            self.project_provider.is_release(),
+        let project_path = self.project_provider.get_project_path()?;
+        let env = self.command_env(config)?;
+        let run_hook = |stage: HookStage| -> Result<()> {
+            hook::run(stage, config.hooks.get(stage), &env, &project_path)
+        };
+        run_hook(HookStage::PreBuild)?;
+        let messages = self.build.build(
+            self.project_provider.as_ref(),
             self.cargo_build.clone(),
+            &config.features,
         )?;
+        run_hook(HookStage::PostBuild)?;
+        let out_dir = message::find_out_dir(&messages, &project_path);
+        self.run_generators(config, &env)?;
+        run_hook(HookStage::PrePackage)?;
+        let vars = self.template_vars(config);
+        let mut resolved = vec![];
         for asset in &config.assets {
-            let source = &asset.source;
-            let dest = &asset.dest;
+            let dest = self.asset_dest(asset)?;
+            let mut files = resolve::resolve_asset(
+                asset,
+                &dest,
+                &project_path,
+                &config.ignore,
+                out_dir.as_deref(),
+            )?;
+            if asset.template {
+                files = files
+                    .into_iter()
+                    .map(|file| file.render(&vars))
+                    .collect::<Result<_>>()?;
+            }
+            resolved.push((Origin::Asset(asset), files));
+        }
+        if let Some(webui) = &config.webui {
+            let files = self.webui_files(webui, &config.module_prop)?;
+            resolved.push((Origin::Module("webui".to_string()), files));
+        }
+        for app in &config.apps {
+            let files = self.app_files(app)?;
+            resolved.push((Origin::Module(format!("app {}", app.name)), files));
+        }
+        resolved.push((
+            Origin::Module("binaries".to_string()),
+            self.binary_files(config)?,
+        ));
+        resolved.push((
+            Origin::Module(DataDir::MIGRATIONS.to_string()),
+            self.migration_files(config)?,
+        ));
+        let staging_path = self.staging_path()?;
+        resolved.push((
+            Origin::Module("module.prop".to_string()),
+            vec![StagedFile {
+                source: StagedSource::Text(config.module_prop.to_string(), 0o644),
+                dest: staging_path.join("module.prop"),
+            }],
+        ));
+        if !config.options.is_empty() {
+            let dir = staging_path.join(InstallOption::DIR);
+            resolve::check_reserved(&resolved, &dir, &project_path)?;
+        }
+        if config
+            .data_dir
+            .as_ref()
+            .is_some_and(|value| !value.migrations.is_empty())
+        {
+            let dir = staging_path.join(DataDir::MIGRATIONS);
+            resolve::check_reserved(&resolved, &dir, &project_path)?;
+        }
+        for file in resolve::merge(resolved, &project_path)? {
+            file.copy()?;
+        }
+        ScriptSet::from_config(config).write(&staging_path)?;
+        run_hook(HookStage::PostPackage)?;
+        Ok(())
+    }
+}
+
+impl DefaultDeploy {
+    /// Optional assets are staged under `options/<name>/` and moved into
+    /// place by the installer once the option is chosen.
+    fn asset_dest(&self, asset: &Asset) -> Result<PathBuf> {
+        let Some(name) = &asset.option else {
+            return Ok(asset.dest.clone());
+        };
+        let staging_path = self.staging_path()?;
+        let relative = asset.dest.strip_prefix(&staging_path)?;
+        let mut result = staging_path;
+        result.push(InstallOption::DIR);
+        result.push(name);
+        result.push(relative);
+        Ok(result)
+    }
+
+    /// Environment of hooks and generate commands.
+    fn command_env(&self, config: &Config) -> Result<Vec<(&'static str, String)>> {
+        let mut result = vec![
+            ("MODULE_ID", config.module_prop.id.clone()),
+            ("TARGET", self.project_provider.get_target().to_string()),
+            ("PROFILE", self.project_provider.get_profile().to_string()),
+            (
+                "STAGING_DIR",
+                self.staging_path()?.to_string_lossy().into_owned(),
+            ),
+        ];
+        if let Some(variant) = self.project_provider.get_variant() {
+            result.push(("VARIANT", variant.to_string()));
+        }
+        Ok(result)
+    }
 
-            if !source.exists() {
-                bail!("Asset source not found: '{}'", asset.source.display());
+    /// Runs the commands of generated assets in declaration order.
+    fn run_generators(&self, config: &Config, env: &[(&str, String)]) -> Result<()> {
+        let project_path = self.project_provider.get_project_path()?;
+        let target_path = self.project_provider.get_target_path()?;
+        for asset in &config.assets {
+            let AssetSource::Generate(generator) = &asset.source else {
+                continue;
+            };
+            let mut stamp = target_path.join(GENERATE_STAMP_DIR);
+            if let Some(package) = self.project_provider.get_package() {
+                stamp.push(package);
             }
+            stamp.push(generate::stamp_name(&generator.output));
+            generate::run(generator, env, &project_path, &stamp)?;
+        }
+        Ok(())
+    }
+
+    /// Collects the values for `{{ name }}` in templated assets.
+    fn template_vars(&self, config: &Config) -> BTreeMap<String, String> {
+        let module_prop = &config.module_prop;
+        let mut result = config.vars.clone();
+        result.insert("id".to_string(), module_prop.id.clone());
+        result.insert("version".to_string(), module_prop.version.clone());
+        result.insert(
+            "version_code".to_string(),
+            module_prop.version_code.to_string(),
+        );
+        result.insert(
+            "target".to_string(),
+            self.project_provider.get_target().to_string(),
+        );
+        result
+    }
 
-            match dest.parent() {
-                Some(value) => {
-                    fs::create_dir_all(value)?;
-                }
-                None => {
-                    bail!("Asset dest failed get parent: '{}'", dest.display());
-                }
+    fn app_files(&self, app: &App) -> Result<Vec<StagedFile>> {
+        if !app.apk.is_file() {
+            bail!("App apk not found: '{}'", app.apk.display());
+        }
+        let staging_path = self.staging_path()?;
+        let mut result = vec![StagedFile {
+            source: StagedSource::File(app.apk.clone()),
+            dest: staging_path
+                .join(app.dir())
+                .join(format!("{}.apk", app.name)),
+        }];
+
+        if app.privileged {
+            let manifest = ApkManifest::read(&app.apk)?;
+            let content = manifest.privapp_permissions(app.permissions.as_deref())?;
+            let file_name = format!("privapp-permissions-{}.xml", manifest.package);
+            result.push(StagedFile {
+                source: StagedSource::Text(content, 0o644),
+                dest: staging_path.join("system/etc/permissions").join(file_name),
+            });
+        }
+        Ok(result)
+    }
+
+    fn binary_files(&self, config: &Config) -> Result<Vec<StagedFile>> {
+        let target_path = self.project_provider.get_target_path()?;
+        let bin_path = self.staging_path()?.join(script::BIN_DIR);
+        let mut result = vec![];
+        for binary in config.binaries() {
+            let source = target_path.join(binary);
+            if !source.is_file() {
+                bail!("Binary not found: '{}'", source.display());
             }
+            result.push(StagedFile {
+                source: StagedSource::File(source),
+                dest: bin_path.join(binary),
+            });
+        }
+        Ok(result)
+    }
 
-            if source.is_file() {
-                fs::copy(source, dest)?;
+    fn migration_files(&self, config: &Config) -> Result<Vec<StagedFile>> {
+        let Some(data_dir) = &config.data_dir else {
+            return Ok(vec![]);
+        };
+        let migrations_path = self.staging_path()?.join(DataDir::MIGRATIONS);
+        let result = data_dir
+            .migrations
+            .iter()
+            .map(|migration| StagedFile {
+                source: StagedSource::File(migration.source.clone()),
+                dest: migrations_path.join(format!("{}.sh", migration.version_code)),
+            })
+            .collect();
+        Ok(result)
+    }
+
+    /// Runs the webui build and returns the files of its output together
+    /// with the generated module shim.
+    fn webui_files(&self, webui: &WebUi, module_prop: &ModuleProp) -> Result<Vec<StagedFile>> {
+        if let Some(command) = &webui.build {
+            println!("Building webui..");
+            let status = Command::new(&command[0])
+                .args(&command[1..])
+                .current_dir(self.project_provider.get_project_path()?)
+                .status()
+                .with_context(|| format!("Failed to run webui build: '{}'", command[0]))?;
+            if !status.success() {
+                bail!("Webui build failed: {}", status);
             }
+        }
+
+        if !webui.source.is_dir() {
+            bail!(
+                "Webui source is not a directory: '{}'",
+                webui.source.display()
+            );
+        }
+        if !webui.source.join(WebUi::INDEX).is_file() {
+            bail!(
+                "Webui source has no {}: '{}'",
+                WebUi::INDEX,
+                webui.source.display()
+            );
+        }
+        if webui.source.join(WebUi::SHIM).exists() {
+            bail!(
+                "Webui source must not contain {}, it is generated: '{}'",
+                WebUi::SHIM,
+                webui.source.display()
+            );
+        }
 
-            if source.is_dir() {
-                let copy_options = fs_extra::dir::CopyOptions::new();
-                fs_extra::dir::copy(source, dest, &copy_options)?;
+        let dest = self.staging_path()?.join(WebUi::DEST);
+        let mut result = vec![];
+        for entry in WalkDir::new(&webui.source)
+            .follow_links(true)
+            .sort_by_file_name()
+        {
+            let entry = entry
+                .with_context(|| format!("Failed to read webui: '{}'", webui.source.display()))?;
+            if entry.file_type().is_dir() {
+                continue;
             }
+            let relative = entry.path().strip_prefix(&webui.source)?;
+            result.push(StagedFile {
+                source: StagedSource::File(entry.path().to_path_buf()),
+                dest: dest.join(relative),
+            });
         }
-        let module_prop_string = config.module_prop.to_string();
-        self.write_module_prop(&module_prop_string)?;
-        Ok(())
+        result.push(StagedFile {
+            source: StagedSource::Text(module_prop.to_js(), 0o644),
+            dest: dest.join(WebUi::SHIM),
+        });
+        Ok(result)
     }
 }
 
-impl DefaultDeploy {
-    fn write_module_prop(&self, content: &str) -> Result<()> {
-        let mut module_prop_path = self.project_provider.get_target_path()?.join("magisk");
-        module_prop_path.push("module.prop");
-        fs::write(module_prop_path, content)?;
-        Ok(())
+/// Arguments of the cargo build. The package manifest is passed
+/// explicitly, so cargo builds the same project the config was read from
+/// regardless of the current directory.
+fn build_args(
+    project: &dyn ProjectProvider,
+    cargo_build: Option<String>,
+    features: &[String],
+) -> Result<Vec<String>> {
+    let mut result: Vec<String> = vec![];
+    if let Some(value) = cargo_build {
+        result.push(value);
+    }
+    result.extend([
+        "build".to_string(),
+        "--message-format=json-render-diagnostics".to_string(),
+        "--manifest-path".to_string(),
+        project.get_manifest_path()?.to_string_lossy().into_owned(),
+        "--target".to_string(),
+        project.get_target().to_string(),
+    ]);
+    if let Some(package) = project.get_package() {
+        result.push("--package".to_string());
+        result.push(package.to_string());
+    }
+    if !features.is_empty() {
+        result.push("--features".to_string());
+        result.push(features.join(","));
     }
+    if project.is_release() {
+        result.push("--release".to_string());
+    }
+    Ok(result)
 }
 
 #[derive(Debug)]
@@ -100,23 +380,27 @@ impl BuildShell {
 }
 
 impl Build for BuildShell {
-    fn build(&self, target: &Target, release: bool, cargo_build: Option<String>) -> Result<()> {
+    fn build(
+        &self,
+        project: &dyn ProjectProvider,
+        cargo_build: Option<String>,
+        features: &[String],
+    ) -> Result<String> {
         let mut proc = Command::new("cargo");
-        if let Some(value) = cargo_build {
-            proc.arg(value);
-        }
-
-        proc.args(["build", "--target", &target.to_string()]);
-        if release {
-            proc.arg("--release");
-        }
+        proc.args(build_args(project, cargo_build, features)?);
 
         println!("Building..");
         println!("---------------------");
-        let mut child = proc.spawn()?;
-        child.wait()?;
+        let output = proc
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .output()
+            .context("Failed to run cargo build")?;
         println!("---------------------");
+        if !output.status.success() {
+            bail!("Cargo build failed: {}", output.status);
+        }
         println!("Done");
-        Ok(())
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
     }
 }
@@ -0,0 +1,115 @@
+#[cfg(test)]
+mod test;
+
+use crate::config::Generator;
+use anyhow::{Context, Result, bail};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    time::UNIX_EPOCH,
+};
+use walkdir::WalkDir;
+
+/// Runs `generator` in `project_path` with `env` set. When the generator
+/// declares inputs, the state of the inputs is recorded in `stamp` and the
+/// command is skipped while the output exists and the state is unchanged.
+pub fn run(
+    generator: &Generator,
+    env: &[(&str, String)],
+    project_path: &Path,
+    stamp: &Path,
+) -> Result<()> {
+    let state = match &generator.inputs {
+        Some(inputs) => Some(input_state(generator, inputs, env)?),
+        None => None,
+    };
+    if let Some(state) = &state
+        && generator.output.exists()
+        && fs::read_to_string(stamp).is_ok_and(|value| &value == state)
+    {
+        println!("Generate '{}' is up to date", generator.command.join(" "));
+        return Ok(());
+    }
+
+    if let Some(parent) = generator.output.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let (program, args) = generator
+        .command
+        .split_first()
+        .context("Invalid generate command: value is empty")?;
+    let status = Command::new(program)
+        .args(args)
+        .current_dir(project_path)
+        .envs(env.iter().map(|(key, value)| (key, value)))
+        .env("OUT", &generator.output)
+        .status()
+        .with_context(|| format!("Failed to run generate command '{}'", program))?;
+    if !status.success() {
+        bail!(
+            "Generate command '{}' failed: {}",
+            generator.command.join(" "),
+            status
+        );
+    }
+    if !generator.output.exists() {
+        bail!(
+            "Generate command '{}' did not write '{}'",
+            generator.command.join(" "),
+            generator.output.display()
+        );
+    }
+
+    if let Some(state) = state {
+        if let Some(parent) = stamp.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(stamp, state)?;
+    }
+    Ok(())
+}
+
+/// Name of the stamp file of a generated `output`, a hash of its full
+/// path. FNV-1a is used as it is stable across toolchains.
+pub fn stamp_name(output: &Path) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in output.as_os_str().as_encoded_bytes() {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}.stamp", hash)
+}
+
+/// Describes the command, its environment and the size and modification
+/// time of every input file.
+fn input_state(
+    generator: &Generator,
+    inputs: &[PathBuf],
+    env: &[(&str, String)],
+) -> Result<String> {
+    let mut result = format!("command={:?}\n", generator.command);
+    for (key, value) in env {
+        result.push_str(&format!("{}={}\n", key, value));
+    }
+    for input in inputs {
+        if !input.exists() {
+            bail!("Generate input not found: '{}'", input.display());
+        }
+        for entry in WalkDir::new(input).sort_by_file_name() {
+            let entry = entry?;
+            if entry.file_type().is_dir() {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            let modified = metadata.modified()?.duration_since(UNIX_EPOCH)?;
+            result.push_str(&format!(
+                "{}\t{}\t{}\n",
+                entry.path().display(),
+                metadata.len(),
+                modified.as_nanos()
+            ));
+        }
+    }
+    Ok(result)
+}
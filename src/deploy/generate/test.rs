@@ -0,0 +1,100 @@
+use super::*;
+use rstest::{fixture, rstest};
+use tempfile::TempDir;
+
+#[rstest]
+fn run_env(project: TempDir) {
+    let root = project.path();
+    let generator = generator(
+        root,
+        r#"printf '%s %s %s' "$MODULE_ID" "$TARGET" "$PROFILE" > "$OUT""#,
+        None,
+    );
+    run(&generator, &env(), root, &root.join("stamp")).unwrap();
+    let content = fs::read_to_string(&generator.output).unwrap();
+    assert_eq!(content, "a_module aarch64-linux-android debug");
+    assert!(!root.join("stamp").exists());
+}
+
+#[rstest]
+fn run_skips_unchanged_inputs(project: TempDir) {
+    let root = project.path();
+    let input = root.join("hosts.txt");
+    fs::write(&input, "a").unwrap();
+    let generator = generator(
+        root,
+        r#"cat hosts.txt >> "$OUT""#,
+        Some(vec![input.clone()]),
+    );
+    let stamp = root.join("stamps/out.stamp");
+    run(&generator, &env(), root, &stamp).unwrap();
+    run(&generator, &env(), root, &stamp).unwrap();
+    assert_eq!(fs::read_to_string(&generator.output).unwrap(), "a");
+
+    fs::write(&input, "bc").unwrap();
+    run(&generator, &env(), root, &stamp).unwrap();
+    assert_eq!(fs::read_to_string(&generator.output).unwrap(), "abc");
+
+    fs::remove_file(&generator.output).unwrap();
+    run(&generator, &env(), root, &stamp).unwrap();
+    assert_eq!(fs::read_to_string(&generator.output).unwrap(), "bc");
+}
+
+#[rstest]
+fn run_without_inputs_always_runs(project: TempDir) {
+    let root = project.path();
+    let generator = generator(root, r#"echo x >> "$OUT""#, None);
+    run(&generator, &env(), root, &root.join("stamp")).unwrap();
+    run(&generator, &env(), root, &root.join("stamp")).unwrap();
+    assert_eq!(fs::read_to_string(&generator.output).unwrap(), "x\nx\n");
+}
+
+#[rstest]
+#[case("exit 3", None)]
+#[case("true", None)]
+#[case(r#"echo x > "$OUT""#, Some("missing.txt"))]
+fn run_err(project: TempDir, #[case] script: &str, #[case] input: Option<&str>) {
+    let root = project.path();
+    let inputs = input.map(|value| vec![root.join(value)]);
+    let generator = generator(root, script, inputs);
+    let result = run(&generator, &env(), root, &root.join("stamp"));
+    assert!(
+        result.is_err(),
+        "result.is_err() not true. script: '{}'",
+        script
+    );
+}
+
+#[rstest]
+fn stamp_name_unique() {
+    let names = [
+        stamp_name(Path::new("/workspace/a_b/c")),
+        stamp_name(Path::new("/workspace/a/b_c")),
+        stamp_name(Path::new("/workspace/crates/daemon/a/b_c")),
+    ];
+    assert_ne!(names[0], names[1]);
+    assert_ne!(names[1], names[2]);
+    assert_eq!(names[0], stamp_name(Path::new("/workspace/a_b/c")));
+    assert!(names[0].ends_with(".stamp"));
+}
+
+#[fixture]
+fn project() -> TempDir {
+    tempfile::tempdir().unwrap()
+}
+
+fn generator(root: &Path, script: &str, inputs: Option<Vec<PathBuf>>) -> Generator {
+    Generator {
+        command: vec!["sh".to_string(), "-c".to_string(), script.to_string()],
+        output: root.join("target/generated/out.txt"),
+        inputs,
+    }
+}
+
+fn env() -> Vec<(&'static str, String)> {
+    vec![
+        ("MODULE_ID", "a_module".to_string()),
+        ("TARGET", "aarch64-linux-android".to_string()),
+        ("PROFILE", "debug".to_string()),
+    ]
+}
@@ -0,0 +1,37 @@
+#[cfg(test)]
+mod test;
+
+use crate::config::HookStage;
+use anyhow::{Context, Result, bail};
+use std::{path::Path, process::Command};
+
+/// Runs the commands of a hook in order from `project_path` with `env` set.
+/// The first failing command stops the deploy.
+pub fn run(
+    stage: HookStage,
+    commands: &[Vec<String>],
+    env: &[(&str, String)],
+    project_path: &Path,
+) -> Result<()> {
+    for command in commands {
+        let (program, args) = command
+            .split_first()
+            .with_context(|| format!("Invalid hooks.{}: command is empty", stage.name()))?;
+        println!("Running {} hook: {}", stage.name(), command.join(" "));
+        let status = Command::new(program)
+            .args(args)
+            .current_dir(project_path)
+            .envs(env.iter().map(|(key, value)| (key, value)))
+            .status()
+            .with_context(|| format!("Failed to run {} hook '{}'", stage.name(), program))?;
+        if !status.success() {
+            bail!(
+                "Hook {} '{}' failed: {}",
+                stage.name(),
+                command.join(" "),
+                status
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use super::*;
+use rstest::rstest;
+use std::fs;
+
+#[rstest]
+fn run_ok() {
+    let project = tempfile::tempdir().unwrap();
+    let root = project.path();
+    let commands = vec![
+        shell(r#"echo "$MODULE_ID $STAGING_DIR" > first.txt"#),
+        vec![
+            "cp".to_string(),
+            "first.txt".to_string(),
+            "second.txt".to_string(),
+        ],
+    ];
+    let env = [
+        ("MODULE_ID", "a_module".to_string()),
+        ("STAGING_DIR", "/staging".to_string()),
+    ];
+    run(HookStage::PostPackage, &commands, &env, root).unwrap();
+    let content = fs::read_to_string(root.join("second.txt")).unwrap();
+    assert_eq!(content, "a_module /staging\n");
+}
+
+#[rstest]
+fn run_stops_on_failure() {
+    let project = tempfile::tempdir().unwrap();
+    let root = project.path();
+    let commands = vec![shell("exit 1"), shell("touch after.txt")];
+    let result = run(HookStage::PreBuild, &commands, &[], root);
+    assert!(result.is_err());
+    assert!(!root.join("after.txt").exists());
+}
+
+#[rstest]
+fn run_missing_program() {
+    let project = tempfile::tempdir().unwrap();
+    let commands = vec![vec!["cargo-magisk-missing-hook".to_string()]];
+    let result = run(HookStage::PostBuild, &commands, &[], project.path());
+    assert!(result.is_err());
+}
+
+fn shell(script: &str) -> Vec<String> {
+    vec!["sh".to_string(), "-c".to_string(), script.to_string()]
+}
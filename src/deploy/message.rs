@@ -0,0 +1,59 @@
+#[cfg(test)]
+mod test;
+
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+const BUILD_SCRIPT_EXECUTED: &str = "build-script-executed";
+
+/// Finds the `OUT_DIR` of the package at `project_path` in the output of
+/// `cargo build --message-format=json`. Lines that are not JSON messages
+/// are skipped.
+pub fn find_out_dir(stdout: &str, project_path: &Path) -> Option<PathBuf> {
+    for line in stdout.lines() {
+        let Ok(message) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        if message["reason"] != BUILD_SCRIPT_EXECUTED {
+            continue;
+        }
+        let Some(package_id) = message["package_id"].as_str() else {
+            continue;
+        };
+        if package_path(package_id).as_deref() != Some(project_path) {
+            continue;
+        }
+        if let Some(out_dir) = message["out_dir"].as_str() {
+            return Some(PathBuf::from(out_dir));
+        }
+    }
+    None
+}
+
+/// Extracts the directory of a local package from its id. Both the
+/// `path+file:///dir#name@1.0.0` and the older `name 1.0.0 (path+file:///dir)`
+/// formats are supported.
+fn package_path(package_id: &str) -> Option<PathBuf> {
+    let (_, rest) = package_id.split_once("path+file://")?;
+    let end = rest.find(['#', ')']).unwrap_or(rest.len());
+    Some(PathBuf::from(percent_decode(&rest[..end])))
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut result: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(hex) = value.get(i + 1..i + 3)
+            && let Ok(byte) = u8::from_str_radix(hex, 16)
+        {
+            result.push(byte);
+            i += 3;
+            continue;
+        }
+        result.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&result).into_owned()
+}
@@ -0,0 +1,39 @@
+use super::*;
+use rstest::rstest;
+
+#[rstest]
+#[case("path+file:///work/my%20module#cargo-magisk@0.1.0", "/work/my module")]
+#[case("path+file:///work/module#0.1.0", "/work/module")]
+#[case("module 0.1.0 (path+file:///work/module)", "/work/module")]
+fn package_path_ok(#[case] package_id: &str, #[case] expected: &str) {
+    assert_eq!(package_path(package_id), Some(PathBuf::from(expected)));
+}
+
+#[rstest]
+fn package_path_registry() {
+    let package_id = "registry+https://github.com/rust-lang/crates.io-index#libc@0.2.177";
+    assert_eq!(package_path(package_id), None);
+}
+
+#[rstest]
+fn find_out_dir_ok() {
+    let stdout = [
+        "warning: not json",
+        r#"{"reason":"build-script-executed","package_id":"registry+https://github.com/rust-lang/crates.io-index#libc@0.2.177","out_dir":"/work/target/debug/build/libc-1/out"}"#,
+        r#"{"reason":"build-script-executed","package_id":"path+file:///work/module-helper#0.1.0","out_dir":"/work/target/debug/build/helper-2/out"}"#,
+        r#"{"reason":"compiler-artifact","package_id":"path+file:///work/module#0.1.0"}"#,
+        r#"{"reason":"build-script-executed","package_id":"path+file:///work/module#0.1.0","out_dir":"/work/target/debug/build/module-3/out"}"#,
+    ]
+    .join("\n");
+    let out_dir = find_out_dir(&stdout, Path::new("/work/module"));
+    assert_eq!(
+        out_dir,
+        Some(PathBuf::from("/work/target/debug/build/module-3/out"))
+    );
+}
+
+#[rstest]
+fn find_out_dir_none() {
+    let stdout = r#"{"reason":"compiler-artifact","package_id":"path+file:///work/module#0.1.0"}"#;
+    assert_eq!(find_out_dir(stdout, Path::new("/work/module")), None);
+}
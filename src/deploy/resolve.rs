@@ -0,0 +1,317 @@
+#[cfg(test)]
+mod test;
+
+use crate::config::{Asset, AssetSource, Symlinks, pattern};
+use crate::template;
+use anyhow::{Context, Result, bail};
+use globset::{GlobBuilder, GlobSet};
+use std::{
+    collections::BTreeMap,
+    fs,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+use walkdir::WalkDir;
+
+#[derive(Debug, PartialEq)]
+pub struct StagedFile {
+    pub source: StagedSource,
+    pub dest: PathBuf,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum StagedSource {
+    /// File copied with its permission bits.
+    File(PathBuf),
+    /// Target of a preserved symlink, staged as a link instead of a copy.
+    Link(PathBuf),
+    /// Text written at deploy time with the given permission bits.
+    Text(String, u32),
+}
+
+impl StagedFile {
+    /// Substitutes template variables in the staged file, keeping the
+    /// permission bits of a file source.
+    pub fn render(self, vars: &BTreeMap<String, String>) -> Result<Self> {
+        let source = match self.source {
+            StagedSource::File(path) => {
+                let text = fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read template: '{}'", path.display()))?;
+                let mode = fs::metadata(&path)?.permissions().mode();
+                let text = template::render(&text, vars)
+                    .with_context(|| format!("Failed to render template: '{}'", path.display()))?;
+                StagedSource::Text(text, mode)
+            }
+            StagedSource::Link(_) => {
+                bail!("Template can not be a symlink: '{}'", self.dest.display())
+            }
+            StagedSource::Text(text, mode) => {
+                let text = template::render(&text, vars).with_context(|| {
+                    format!("Failed to render template: '{}'", self.dest.display())
+                })?;
+                StagedSource::Text(text, mode)
+            }
+        };
+        Ok(Self {
+            source,
+            dest: self.dest,
+        })
+    }
+
+    /// Copies the file into staging, creating parent directories as needed.
+    /// An existing file at `dest` is replaced, so several assets can be
+    /// merged into one directory. Permission bits are kept.
+    pub fn copy(&self) -> Result<()> {
+        match self.dest.parent() {
+            Some(value) => fs::create_dir_all(value)?,
+            None => bail!("Asset dest failed get parent: '{}'", self.dest.display()),
+        }
+        // Never write through a link staged by an earlier asset.
+        if self
+            .dest
+            .symlink_metadata()
+            .is_ok_and(|value| !value.is_dir())
+        {
+            fs::remove_file(&self.dest)?;
+        }
+
+        match &self.source {
+            StagedSource::File(path) => {
+                fs::copy(path, &self.dest)
+                    .with_context(|| format!("Failed to copy asset: '{}'", path.display()))?;
+                let permissions = fs::metadata(path)?.permissions();
+                fs::set_permissions(&self.dest, permissions)?;
+            }
+            StagedSource::Link(link) => {
+                std::os::unix::fs::symlink(link, &self.dest).with_context(|| {
+                    format!("Failed to create symlink: '{}'", self.dest.display())
+                })?;
+            }
+            StagedSource::Text(text, mode) => {
+                fs::write(&self.dest, text)?;
+                fs::set_permissions(&self.dest, fs::Permissions::from_mode(*mode))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Expands `asset` into the files it stages under `dest`. Files found by
+/// globs and directories are filtered by the asset `exclude` patterns and by
+/// `ignore`, which is matched against paths relative to `project_path`.
+///
+/// A directory source is staged as `dest/<name>/...`, or as `dest/...` when
+/// the asset copies its contents. `out_dir` is the `OUT_DIR` of the crate
+/// build script, if it has one.
+pub fn resolve_asset(
+    asset: &Asset,
+    dest: &Path,
+    project_path: &Path,
+    ignore: &GlobSet,
+    out_dir: Option<&Path>,
+) -> Result<Vec<StagedFile>> {
+    let source = match &asset.source {
+        AssetSource::Path(value) => value.clone(),
+        AssetSource::Generate(value) => value.output.clone(),
+        AssetSource::OutDir(value) => match out_dir {
+            Some(out_dir) => out_dir.join(value),
+            None => bail!(
+                "Asset source needs OUT_DIR, but the build script did not run: '{}'",
+                value.display()
+            ),
+        },
+        AssetSource::Content(value) => {
+            return Ok(vec![StagedFile {
+                source: StagedSource::Text(value.clone(), 0o644),
+                dest: dest.to_path_buf(),
+            }]);
+        }
+    };
+
+    if let Some((base, pattern)) = pattern::split_glob(&source) {
+        let matcher = GlobBuilder::new(&pattern)
+            .literal_separator(true)
+            .build()
+            .with_context(|| format!("Invalid asset glob: '{}'", source.display()))?
+            .compile_matcher();
+        let result = walk(&base, asset, project_path, ignore, |relative| {
+            matcher.is_match(relative).then(|| dest.join(relative))
+        })?;
+        if result.is_empty() {
+            bail!("Asset glob matched no files: '{}'", source.display());
+        }
+        return Ok(result);
+    }
+
+    if source.is_file() {
+        return Ok(vec![StagedFile {
+            source: StagedSource::File(source.clone()),
+            dest: dest.to_path_buf(),
+        }]);
+    }
+
+    if source.is_dir() {
+        if asset.template {
+            bail!("Template asset must be a file: '{}'", source.display());
+        }
+        let dest = match asset.contents {
+            true => dest.to_path_buf(),
+            false => {
+                let dir_name = source
+                    .file_name()
+                    .with_context(|| format!("Asset source has no name: '{}'", source.display()))?;
+                dest.join(dir_name)
+            }
+        };
+        return walk(&source, asset, project_path, ignore, |relative| {
+            Some(dest.join(relative))
+        });
+    }
+
+    bail!("Asset source not found: '{}'", source.display());
+}
+
+/// Where staged files come from, named in conflict errors.
+#[derive(Debug)]
+pub enum Origin<'a> {
+    Asset(&'a Asset),
+    /// Files cargo-magisk stages itself, e.g. `module.prop`. They are never
+    /// replaced by an asset.
+    Module(String),
+}
+
+impl Origin<'_> {
+    fn overwrite(&self) -> bool {
+        match self {
+            Origin::Asset(asset) => asset.overwrite,
+            Origin::Module(_) => false,
+        }
+    }
+
+    fn describe(&self, project_path: &Path) -> String {
+        let asset = match self {
+            Origin::Asset(value) => value,
+            Origin::Module(name) => return name.clone(),
+        };
+        match &asset.source {
+            AssetSource::Path(value) => {
+                let source = value.strip_prefix(project_path).unwrap_or(value);
+                format!("{}", source.display())
+            }
+            AssetSource::Content(_) => "inline content".to_string(),
+            AssetSource::OutDir(value) => format!("out_dir:{}", value.display()),
+            AssetSource::Generate(value) => format!("generate:{}", value.command.join(" ")),
+        }
+    }
+}
+
+/// Combines the files resolved for every asset and module file into one
+/// list, failing when two of them stage the same dest or one stages a file
+/// where another expects a directory. A later asset with `overwrite`
+/// replaces the earlier file.
+pub fn merge(
+    resolved: Vec<(Origin<'_>, Vec<StagedFile>)>,
+    project_path: &Path,
+) -> Result<Vec<StagedFile>> {
+    let origins: Vec<String> = resolved
+        .iter()
+        .map(|(origin, _)| origin.describe(project_path))
+        .collect();
+    let mut result: BTreeMap<PathBuf, (usize, StagedFile)> = BTreeMap::new();
+    for (index, (origin, files)) in resolved.into_iter().enumerate() {
+        for file in files {
+            if let Some((other, _)) = result.get(&file.dest)
+                && !origin.overwrite()
+            {
+                bail!(
+                    "Asset dest conflict: '{}' is staged by both '{}' and '{}', set overwrite = true on the latter to replace it",
+                    file.dest.display(),
+                    origins[*other],
+                    origins[index]
+                );
+            }
+            result.insert(file.dest.clone(), (index, file));
+        }
+    }
+
+    for (dest, (index, _)) in &result {
+        for parent in dest.ancestors().skip(1) {
+            if let Some((other, _)) = result.get(parent) {
+                bail!(
+                    "Asset dest overlap: '{}' from '{}' is a file, but '{}' stages '{}' inside it",
+                    parent.display(),
+                    origins[*other],
+                    origins[*index],
+                    dest.display()
+                );
+            }
+        }
+    }
+    Ok(result.into_values().map(|(_, file)| file).collect())
+}
+
+/// Fails when an asset outside of the install options stages a file in
+/// `dir`, a directory the installer moves or removes.
+pub fn check_reserved(
+    resolved: &[(Origin<'_>, Vec<StagedFile>)],
+    dir: &Path,
+    project_path: &Path,
+) -> Result<()> {
+    for (origin, files) in resolved {
+        let Origin::Asset(asset) = origin else {
+            continue;
+        };
+        if asset.option.is_some() {
+            continue;
+        }
+        if let Some(file) = files.iter().find(|file| file.dest.starts_with(dir)) {
+            bail!(
+                "Asset dest conflict: '{}' from '{}' is inside '{}', which is reserved for the installer",
+                file.dest.display(),
+                origin.describe(project_path),
+                dir.display()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn walk(
+    base: &Path,
+    asset: &Asset,
+    project_path: &Path,
+    ignore: &GlobSet,
+    map: impl Fn(&Path) -> Option<PathBuf>,
+) -> Result<Vec<StagedFile>> {
+    let mut result: Vec<StagedFile> = vec![];
+    if !base.is_dir() {
+        return Ok(result);
+    }
+
+    let follow = asset.symlinks == Symlinks::Follow;
+    for entry in WalkDir::new(base).follow_links(follow).sort_by_file_name() {
+        let entry = entry.with_context(|| format!("Failed to read asset: '{}'", base.display()))?;
+        if entry.file_type().is_dir() {
+            continue;
+        }
+
+        let path = entry.path();
+        let relative = path.strip_prefix(base)?;
+        if asset.exclude.is_match(relative) {
+            continue;
+        }
+        if let Ok(value) = path.strip_prefix(project_path)
+            && ignore.is_match(value)
+        {
+            continue;
+        }
+        if let Some(dest) = map(relative) {
+            let source = match entry.path_is_symlink() && !follow {
+                true => StagedSource::Link(fs::read_link(path)?),
+                false => StagedSource::File(path.to_path_buf()),
+            };
+            result.push(StagedFile { source, dest });
+        }
+    }
+    Ok(result)
+}
@@ -0,0 +1,401 @@
+use super::*;
+use crate::config::pattern;
+use rstest::{fixture, rstest};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use tempfile::TempDir;
+
+#[rstest]
+fn resolve_asset_glob(project: TempDir) {
+    let root = project.path();
+    let asset = asset(root.join("assets/etc/**/*.conf"), &[]);
+    let files = resolve_asset(&asset, &root.join("out"), root, &GlobSet::empty(), None).unwrap();
+    assert_eq!(
+        files,
+        [
+            staged(root, "assets/etc/a.conf", "out/a.conf"),
+            staged(root, "assets/etc/deep/b.conf", "out/deep/b.conf"),
+            staged(root, "assets/etc/deep/tmp/c.conf", "out/deep/tmp/c.conf"),
+        ]
+    );
+}
+
+#[rstest]
+fn resolve_asset_glob_single_level(project: TempDir) {
+    let root = project.path();
+    let asset = asset(root.join("assets/etc/*.conf"), &[]);
+    let files = resolve_asset(&asset, &root.join("out"), root, &GlobSet::empty(), None).unwrap();
+    assert_eq!(files, [staged(root, "assets/etc/a.conf", "out/a.conf")]);
+}
+
+#[rstest]
+fn resolve_asset_glob_alternation(project: TempDir) {
+    let root = project.path();
+    let asset = asset(root.join("assets/etc/*.{conf,bak}"), &[]);
+    let files = resolve_asset(&asset, &root.join("out"), root, &GlobSet::empty(), None).unwrap();
+    assert_eq!(
+        files,
+        [
+            staged(root, "assets/etc/a.bak", "out/a.bak"),
+            staged(root, "assets/etc/a.conf", "out/a.conf"),
+        ]
+    );
+}
+
+#[rstest]
+fn resolve_asset_exclude_and_ignore(project: TempDir) {
+    let root = project.path();
+    let asset = asset(root.join("assets/etc/**/*"), &["tmp/"]);
+    let ignore = pattern::compile(&["*.bak".to_string()]).unwrap();
+    let files = resolve_asset(&asset, &root.join("out"), root, &ignore, None).unwrap();
+    assert_eq!(
+        files,
+        [
+            staged(root, "assets/etc/a.conf", "out/a.conf"),
+            staged(root, "assets/etc/deep/b.conf", "out/deep/b.conf"),
+        ]
+    );
+}
+
+#[rstest]
+fn resolve_asset_file_ignores_patterns(project: TempDir) {
+    let root = project.path();
+    let asset = asset(root.join("assets/etc/a.bak"), &["*.bak"]);
+    let ignore = pattern::compile(&["*.bak".to_string()]).unwrap();
+    let files = resolve_asset(&asset, &root.join("out/a.bak"), root, &ignore, None).unwrap();
+    assert_eq!(files, [staged(root, "assets/etc/a.bak", "out/a.bak")]);
+}
+
+#[rstest]
+fn resolve_asset_err(
+    project: TempDir,
+    #[values("assets/etc/**/*.txt", "assets/none/*.conf", "assets/missing.conf")] source: &str,
+) {
+    let root = project.path();
+    let asset = asset(root.join(source), &[]);
+    let result = resolve_asset(&asset, &root.join("out"), root, &GlobSet::empty(), None);
+    assert!(
+        result.is_err(),
+        "result.is_err() not true. source: '{}'",
+        source
+    );
+}
+
+#[rstest]
+fn resolve_asset_glob_all_excluded(project: TempDir) {
+    let root = project.path();
+    let asset = asset(root.join("assets/etc/*.conf"), &["a.conf"]);
+    let result = resolve_asset(&asset, &root.join("out"), root, &GlobSet::empty(), None);
+    assert!(result.is_err());
+}
+
+#[rstest]
+#[case(false, "out/etc/a.conf")]
+#[case(true, "out/a.conf")]
+fn resolve_asset_dir(project: TempDir, #[case] contents: bool, #[case] expected: &str) {
+    let root = project.path();
+    let mut asset = asset(root.join("assets/etc"), &["deep/"]);
+    asset.contents = contents;
+    let files = resolve_asset(&asset, &root.join("out"), root, &GlobSet::empty(), None).unwrap();
+    assert_eq!(
+        files,
+        [
+            staged(root, "assets/etc/a.bak", &expected.replace(".conf", ".bak")),
+            staged(root, "assets/etc/a.conf", expected)
+        ]
+    );
+}
+
+#[rstest]
+fn resolve_asset_symlink_preserve(project: TempDir) {
+    let root = project.path();
+    std::os::unix::fs::symlink("a.conf", root.join("assets/etc/link.conf")).unwrap();
+    std::os::unix::fs::symlink("/system/bin/sh", root.join("assets/etc/sh")).unwrap();
+    let asset = asset(root.join("assets/etc/*"), &[]);
+    let files = resolve_asset(&asset, &root.join("out"), root, &GlobSet::empty(), None).unwrap();
+    for file in &files {
+        file.copy().unwrap();
+    }
+
+    let link = root.join("out/link.conf");
+    assert_eq!(fs::read_link(&link).unwrap(), PathBuf::from("a.conf"));
+    assert_eq!(fs::read_to_string(&link).unwrap(), "assets/etc/a.conf");
+    let sh = root.join("out/sh");
+    assert_eq!(fs::read_link(sh).unwrap(), PathBuf::from("/system/bin/sh"));
+}
+
+#[rstest]
+fn resolve_asset_symlink_follow(project: TempDir) {
+    let root = project.path();
+    std::os::unix::fs::symlink("deep", root.join("assets/etc/linked")).unwrap();
+    let mut asset = asset(root.join("assets/etc"), &["deep/", "*.bak"]);
+    asset.contents = true;
+    asset.symlinks = Symlinks::Follow;
+    let files = resolve_asset(&asset, &root.join("out"), root, &GlobSet::empty(), None).unwrap();
+    assert_eq!(
+        files,
+        [
+            staged(root, "assets/etc/a.conf", "out/a.conf"),
+            staged(root, "assets/etc/linked/b.conf", "out/linked/b.conf"),
+            staged(
+                root,
+                "assets/etc/linked/tmp/c.conf",
+                "out/linked/tmp/c.conf"
+            ),
+        ]
+    );
+    for file in &files {
+        file.copy().unwrap();
+    }
+    let copied = root.join("out/linked/b.conf");
+    assert!(
+        !fs::symlink_metadata(root.join("out/linked"))
+            .unwrap()
+            .is_symlink()
+    );
+    assert_eq!(
+        fs::read_to_string(copied).unwrap(),
+        "assets/etc/deep/b.conf"
+    );
+}
+
+#[rstest]
+fn staged_file_copy_keeps_mode(project: TempDir) {
+    let root = project.path();
+    let source = root.join("assets/etc/a.conf");
+    fs::set_permissions(&source, fs::Permissions::from_mode(0o755)).unwrap();
+    let file = staged(root, "assets/etc/a.conf", "out/bin/a.conf");
+    file.copy().unwrap();
+    let mode = fs::metadata(&file.dest).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o755);
+}
+
+#[rstest]
+fn resolve_asset_merge_dirs(project: TempDir) {
+    let root = project.path();
+    let other = root.join("overlay/etc/extra.conf");
+    fs::create_dir_all(other.parent().unwrap()).unwrap();
+    fs::write(&other, "overlay").unwrap();
+    fs::write(root.join("overlay/etc/a.conf"), "overlay").unwrap();
+
+    let dest = root.join("out/system/etc");
+    for source in ["assets/etc", "overlay/etc"] {
+        let mut asset = asset(root.join(source), &["deep/"]);
+        asset.contents = true;
+        for file in resolve_asset(&asset, &dest, root, &GlobSet::empty(), None).unwrap() {
+            file.copy().unwrap();
+        }
+    }
+
+    let mut names: Vec<String> = fs::read_dir(&dest)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["a.bak", "a.conf", "extra.conf"]);
+    assert_eq!(fs::read_to_string(dest.join("a.conf")).unwrap(), "overlay");
+}
+
+#[rstest]
+fn merge_conflict(project: TempDir) {
+    let root = project.path();
+    let first = asset(root.join("assets/etc"), &[]);
+    let second = asset(root.join("assets/etc/a.conf"), &[]);
+    let dest = root.join("out");
+    let resolved = vec![
+        resolve(&first, &dest, root),
+        resolve(&second, &dest.join("etc/a.conf"), root),
+    ];
+    let err = merge(resolved, root).unwrap_err().to_string();
+    assert!(err.contains("'assets/etc'"), "{}", err);
+    assert!(err.contains("'assets/etc/a.conf'"), "{}", err);
+}
+
+#[rstest]
+fn merge_overwrite(project: TempDir) {
+    let root = project.path();
+    let first = asset(root.join("assets/etc"), &["deep/"]);
+    let mut second = asset(root.join("assets/etc/deep/b.conf"), &[]);
+    second.overwrite = true;
+    let dest = root.join("out");
+    let resolved = vec![
+        resolve(&first, &dest, root),
+        resolve(&second, &dest.join("etc/a.conf"), root),
+    ];
+    let files = merge(resolved, root).unwrap();
+    assert_eq!(
+        files,
+        [
+            staged(root, "assets/etc/a.bak", "out/etc/a.bak"),
+            staged(root, "assets/etc/deep/b.conf", "out/etc/a.conf"),
+        ]
+    );
+}
+
+#[rstest]
+fn merge_overlap(project: TempDir, #[values(false, true)] overwrite: bool) {
+    let root = project.path();
+    let mut first = asset(root.join("assets/etc/a.conf"), &[]);
+    first.overwrite = overwrite;
+    let mut second = asset(root.join("assets/etc"), &[]);
+    second.overwrite = overwrite;
+    let dest = root.join("out");
+    let resolved = vec![
+        resolve(&first, &dest.join("etc"), root),
+        resolve(&second, &dest, root),
+    ];
+    let err = merge(resolved, root).unwrap_err().to_string();
+    assert!(err.contains("'assets/etc/a.conf'"), "{}", err);
+    assert!(err.contains("'assets/etc'"), "{}", err);
+}
+
+#[rstest]
+fn merge_module_conflict(project: TempDir) {
+    let root = project.path();
+    let mut first = asset(root.join("assets/etc/a.conf"), &[]);
+    first.overwrite = true;
+    let dest = root.join("out");
+    let module_prop = StagedFile {
+        source: StagedSource::Text("id=a_module\n".to_string(), 0o644),
+        dest: dest.join("module.prop"),
+    };
+    let resolved = vec![
+        resolve(&first, &dest.join("module.prop"), root),
+        (Origin::Module("module.prop".to_string()), vec![module_prop]),
+    ];
+    let err = merge(resolved, root).unwrap_err().to_string();
+    assert!(err.contains("'assets/etc/a.conf'"), "{}", err);
+    assert!(err.contains("'module.prop'"), "{}", err);
+}
+
+#[rstest]
+fn check_reserved_options(project: TempDir) {
+    let root = project.path();
+    let dest = root.join("out");
+    let plain = asset(root.join("assets/etc/a.conf"), &[]);
+    let mut optional = asset(root.join("assets/etc/a.bak"), &[]);
+    optional.option = Some("extra".to_string());
+    let reserved = dest.join("options");
+
+    let resolved = vec![resolve(&optional, &reserved.join("extra/a.bak"), root)];
+    check_reserved(&resolved, &reserved, root).unwrap();
+
+    let resolved = vec![resolve(&plain, &reserved.join("a.conf"), root)];
+    let err = check_reserved(&resolved, &reserved, root)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("'assets/etc/a.conf'"), "{}", err);
+}
+
+#[rstest]
+fn resolve_asset_content(project: TempDir) {
+    let root = project.path();
+    let mut asset = asset(PathBuf::new(), &[]);
+    asset.source = AssetSource::Content("id={{ id }}\n".to_string());
+    let dest = root.join("out/system/etc/a.conf");
+    let files = resolve_asset(&asset, &dest, root, &GlobSet::empty(), None).unwrap();
+    let file = files.into_iter().next().unwrap().render(&vars()).unwrap();
+    file.copy().unwrap();
+    assert_eq!(fs::read_to_string(&dest).unwrap(), "id=a_module\n");
+    let mode = fs::metadata(&dest).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o644);
+}
+
+#[rstest]
+fn staged_file_render_keeps_mode(project: TempDir) {
+    let root = project.path();
+    let source = root.join("assets/run.sh");
+    fs::write(&source, "echo {{ id }} {{version}}").unwrap();
+    fs::set_permissions(&source, fs::Permissions::from_mode(0o755)).unwrap();
+    let file = staged(root, "assets/run.sh", "out/run.sh")
+        .render(&vars())
+        .unwrap();
+    file.copy().unwrap();
+    let dest = root.join("out/run.sh");
+    assert_eq!(fs::read_to_string(&dest).unwrap(), "echo a_module 1.2.3");
+    let mode = fs::metadata(&dest).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o755);
+}
+
+#[rstest]
+fn staged_file_render_undefined(project: TempDir) {
+    let root = project.path();
+    fs::write(root.join("assets/a.conf"), "{{ undefined }}").unwrap();
+    let result = staged(root, "assets/a.conf", "out/a.conf").render(&vars());
+    assert!(result.is_err());
+}
+
+#[rstest]
+fn resolve_asset_template_dir(project: TempDir) {
+    let root = project.path();
+    let mut asset = asset(root.join("assets/etc"), &[]);
+    asset.template = true;
+    let result = resolve_asset(&asset, &root.join("out"), root, &GlobSet::empty(), None);
+    assert!(result.is_err());
+}
+
+#[rstest]
+fn resolve_asset_out_dir(project: TempDir) {
+    let root = project.path();
+    let mut asset = asset(PathBuf::new(), &[]);
+    asset.source = AssetSource::OutDir(PathBuf::from("deep/*.conf"));
+    let out_dir = root.join("assets/etc");
+    let dest = root.join("out");
+    let files = resolve_asset(&asset, &dest, root, &GlobSet::empty(), Some(&out_dir)).unwrap();
+    assert_eq!(
+        files,
+        [staged(root, "assets/etc/deep/b.conf", "out/b.conf")]
+    );
+
+    let result = resolve_asset(&asset, &dest, root, &GlobSet::empty(), None);
+    assert!(result.is_err());
+}
+
+fn vars() -> BTreeMap<String, String> {
+    BTreeMap::from([
+        ("id".to_string(), "a_module".to_string()),
+        ("version".to_string(), "1.2.3".to_string()),
+    ])
+}
+
+fn resolve<'a>(asset: &'a Asset, dest: &Path, root: &Path) -> (Origin<'a>, Vec<StagedFile>) {
+    let files = resolve_asset(asset, dest, root, &GlobSet::empty(), None).unwrap();
+    (Origin::Asset(asset), files)
+}
+
+#[fixture]
+fn project() -> TempDir {
+    let project = tempfile::tempdir().unwrap();
+    for file in [
+        "assets/etc/a.conf",
+        "assets/etc/a.bak",
+        "assets/etc/deep/b.conf",
+        "assets/etc/deep/tmp/c.conf",
+    ] {
+        let path = project.path().join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, file).unwrap();
+    }
+    project
+}
+
+fn asset(source: PathBuf, exclude: &[&str]) -> Asset {
+    let exclude: Vec<String> = exclude.iter().map(|value| value.to_string()).collect();
+    Asset {
+        source: AssetSource::Path(source),
+        dest: PathBuf::new(),
+        option: None,
+        exclude: pattern::compile(&exclude).unwrap(),
+        contents: false,
+        symlinks: Symlinks::Preserve,
+        overwrite: false,
+        template: false,
+    }
+}
+
+fn staged(root: &Path, source: &str, dest: &str) -> StagedFile {
+    StagedFile {
+        source: StagedSource::File(root.join(source)),
+        dest: root.join(dest),
+    }
+}
@@ -0,0 +1,174 @@
+use super::*;
+use crate::config::PackageInfo;
+use crate::project::test_support::MockProject;
+use rstest::{fixture, rstest};
+use std::path::Path;
+use tempfile::TempDir;
+
+#[rstest]
+#[case(
+    None,
+    None,
+    &[],
+    false,
+    "build --message-format=json-render-diagnostics --manifest-path /workspace/crates/daemon/Cargo.toml --target aarch64-linux-android"
+)]
+#[case(
+    Some("+nightly"),
+    Some("daemon"),
+    &[],
+    true,
+    "+nightly build --message-format=json-render-diagnostics --manifest-path /workspace/crates/daemon/Cargo.toml --target aarch64-linux-android --package daemon --release"
+)]
+#[case(
+    None,
+    None,
+    &["lite", "tls"],
+    false,
+    "build --message-format=json-render-diagnostics --manifest-path /workspace/crates/daemon/Cargo.toml --target aarch64-linux-android --features lite,tls"
+)]
+fn build_args_ok(
+    #[case] cargo_build: Option<&str>,
+    #[case] package: Option<&str>,
+    #[case] features: &[&str],
+    #[case] release: bool,
+    #[case] expected: &str,
+) {
+    let project = MockProject {
+        project_path: PathBuf::from("/workspace/crates/daemon"),
+        package: package.map(str::to_string),
+        release,
+        ..Default::default()
+    };
+    let features: Vec<String> = features.iter().map(|value| value.to_string()).collect();
+    let args = build_args(&project, cargo_build.map(str::to_string), &features).unwrap();
+    assert_eq!(args.join(" "), expected);
+}
+
+#[rstest]
+fn build_shell_fails_on_cargo_error() {
+    let project = MockProject::default();
+    let result = BuildShell::new().build(&project, Some("--no-such-flag".to_string()), &[]);
+    let err = result.unwrap_err().to_string();
+    assert!(err.starts_with("Cargo build failed"), "{}", err);
+}
+
+#[rstest]
+fn template_vars_match_module_prop() {
+    let project = tempfile::tempdir().unwrap();
+    let manifest_path = project.path().join("Cargo.toml");
+    fs::write(
+        &manifest_path,
+        "[package]\nname = \"daemon\"\nversion = \"1.2.3\"\n\n\
+         [package.metadata.magisk]\nid = \"a_module\"\nauthor = \"author\"\n",
+    )
+    .unwrap();
+    let project_provider: Rc<dyn ProjectProvider> = Rc::new(MockProject::default());
+    let package = PackageInfo {
+        name: "daemon".to_string(),
+        version: "1.2.3".to_string(),
+        ..Default::default()
+    };
+    let config = Config::load_with_path(manifest_path, None, &project_provider, &package).unwrap();
+    let deploy = DefaultDeploy::new(project_provider, None);
+
+    let vars = deploy.template_vars(&config);
+    assert_eq!(vars["version_code"], "010203900");
+    assert!(
+        config
+            .module_prop
+            .to_string()
+            .contains("versionCode=010203900\n")
+    );
+}
+
+#[rstest]
+fn webui_files_layout(project: TempDir) {
+    let root = project.path();
+    let webui = WebUi {
+        source: root.join("webui"),
+        build: Some(vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            "echo built > webui/built.txt".to_string(),
+        ]),
+    };
+    let deploy = deploy(root);
+    let files = deploy.webui_files(&webui, &module_prop()).unwrap();
+
+    let dest = root.join("target/magisk").join(WebUi::DEST);
+    let expected = [
+        StagedFile {
+            source: StagedSource::File(root.join("webui/built.txt")),
+            dest: dest.join("built.txt"),
+        },
+        StagedFile {
+            source: StagedSource::File(root.join("webui/index.html")),
+            dest: dest.join("index.html"),
+        },
+        StagedFile {
+            source: StagedSource::File(root.join("webui/js/app.js")),
+            dest: dest.join("js/app.js"),
+        },
+        StagedFile {
+            source: StagedSource::Text(module_prop().to_js(), 0o644),
+            dest: dest.join(WebUi::SHIM),
+        },
+    ];
+    assert_eq!(files, expected);
+}
+
+#[rstest]
+#[case("webui/index.html", None, "is not a directory")]
+#[case("webui/js", None, "has no index.html")]
+#[case("webui", Some("touch webui/module.js"), "must not contain module.js")]
+#[case("webui", Some("exit 3"), "Webui build failed")]
+fn webui_files_err(
+    project: TempDir,
+    #[case] source: &str,
+    #[case] build: Option<&str>,
+    #[case] expected: &str,
+) {
+    let root = project.path();
+    let webui = WebUi {
+        source: root.join(source),
+        build: build.map(|value| vec!["sh".to_string(), "-c".to_string(), value.to_string()]),
+    };
+    let deploy = deploy(root);
+    let err = deploy
+        .webui_files(&webui, &module_prop())
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains(expected), "{}", err);
+}
+
+#[fixture]
+fn project() -> TempDir {
+    let project = tempfile::tempdir().unwrap();
+    for file in ["webui/index.html", "webui/js/app.js"] {
+        let path = project.path().join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, file).unwrap();
+    }
+    project
+}
+
+fn deploy(root: &Path) -> DefaultDeploy {
+    let project_provider: Rc<dyn ProjectProvider> = Rc::new(MockProject {
+        project_path: root.to_path_buf(),
+        target_path: root.join("target"),
+        ..Default::default()
+    });
+    DefaultDeploy::new(project_provider, None)
+}
+
+fn module_prop() -> ModuleProp {
+    ModuleProp::new(
+        "a_module".to_string(),
+        "name".to_string(),
+        "1.0.0".to_string(),
+        "author".to_string(),
+        None,
+    )
+    .unwrap()
+}
@@ -1,15 +1,23 @@
+mod apk;
 mod config;
 mod deploy;
+mod metadata;
 mod project;
+mod script;
+mod template;
 
-use anyhow::Result;
-use clap::{Parser, Subcommand};
+use anyhow::{Context, Result, bail};
+use clap::{Args, Parser, Subcommand};
 use std::{env, path::PathBuf, rc::Rc};
 
 use crate::{
     config::Config,
     deploy::{DefaultDeploy, Deploy},
-    project::{DefaultManifest, DefaultProject, ManifestProvider, ProjectProvider, Target},
+    metadata::CargoMetadata,
+    project::{
+        DefaultManifest, DefaultProject, Manager, ManifestProvider, PackageLayout, PackageManifest,
+        ProjectProvider, Target,
+    },
 };
 
 #[derive(Parser, Debug)]
@@ -21,16 +29,36 @@ struct Cli {
 
 #[derive(Subcommand, Debug)]
 enum Commands {
-    Build {
-        #[clap(short, long)]
-        target: Target,
-        #[clap(long)]
-        release: bool,
-        #[clap(long)]
-        cargo_build: Option<String>,
-        #[clap(long)]
-        manifest_path: Option<PathBuf>,
-    },
+    Build(BuildArgs),
+}
+
+#[derive(Args, Debug)]
+struct BuildArgs {
+    #[clap(short, long)]
+    target: Target,
+    #[clap(long)]
+    release: bool,
+    #[clap(long, value_enum, default_value_t = Manager::Magisk)]
+    manager: Manager,
+    #[clap(long)]
+    cargo_build: Option<String>,
+    #[clap(long)]
+    manifest_path: Option<PathBuf>,
+    /// Module config used instead of Magisk.toml and Cargo metadata.
+    #[clap(long)]
+    config: Option<PathBuf>,
+    /// Workspace package to build into a module, may be repeated.
+    #[clap(short, long = "package")]
+    packages: Vec<String>,
+    /// Build every workspace package with a module config.
+    #[clap(long, conflicts_with = "packages")]
+    workspace: bool,
+    /// Module variant to build, from `[package.metadata.magisk.variants]`.
+    #[clap(long)]
+    variant: Option<String>,
+    /// Build every declared variant, each staged separately.
+    #[clap(long, conflicts_with = "variant")]
+    all_variants: bool,
 }
 
 fn main() -> Result<()> {
@@ -42,37 +70,72 @@ fn main() -> Result<()> {
     let cli = Cli::parse_from(args);
 
     match cli.command {
-        Commands::Build {
-            target,
-            release,
-            cargo_build,
-            manifest_path,
-        } => {
-            build_cmd(target, release, cargo_build, manifest_path)?;
+        Commands::Build(args) => {
+            build_cmd(args)?;
         }
     }
     Ok(())
 }
 
-fn build_cmd(
-    target: Target,
-    release: bool,
-    cargo_build: Option<String>,
-    manifest_path: Option<PathBuf>,
-) -> Result<()> {
-    let manifest_provider: Rc<dyn ManifestProvider> = Rc::new(DefaultManifest::new());
-    let project_provider: Rc<dyn ProjectProvider> = Rc::new(DefaultProject::new(
-        target,
-        release,
-        manifest_provider.clone(),
-    ));
-    let config = match manifest_path {
-        Some(value) => Config::load_with_path(value, &project_provider)?,
-        None => Config::load(&manifest_provider, &project_provider)?,
+fn build_cmd(args: BuildArgs) -> Result<()> {
+    let manifest_provider: Rc<dyn ManifestProvider> = match args.manifest_path {
+        Some(value) => Rc::new(PackageManifest::new(value)),
+        None => Rc::new(DefaultManifest::new()),
     };
-    let deploy = DefaultDeploy::new(project_provider, cargo_build);
+    let manifest_path = manifest_provider.find_manifest_path()?;
+    let metadata = CargoMetadata::load(&manifest_path)?;
+    let packages = metadata.select(&args.packages, args.workspace, &manifest_path)?;
+    if args.config.is_some() && packages.len() > 1 {
+        bail!("--config can not be used when building several packages");
+    }
 
-    deploy.deploy(&config)?;
+    let separate_staging = metadata.separate_staging();
+    for package in packages {
+        let manifest_provider: Rc<dyn ManifestProvider> =
+            Rc::new(PackageManifest::new(package.manifest_path.clone()));
+        let layout = PackageLayout {
+            name: package.name.clone(),
+            target_dir: metadata.target_directory.clone(),
+            separate_staging,
+        };
+        let package_info = metadata.package_info(package)?;
+        let project = |variant: Option<String>| -> Rc<dyn ProjectProvider> {
+            Rc::new(DefaultProject::new(
+                args.target.clone(),
+                args.release,
+                args.manager.clone(),
+                manifest_provider.clone(),
+                Some(layout.clone()),
+                variant,
+            ))
+        };
+        let load = |project_provider: &Rc<dyn ProjectProvider>| {
+            Config::load(
+                &manifest_provider,
+                args.config.clone(),
+                project_provider,
+                &package_info,
+            )
+            .with_context(|| format!("Failed to load config of package '{}'", package.name))
+        };
+
+        let variants: Vec<Option<String>> = if args.all_variants {
+            let config = load(&project(None))?;
+            if config.variants.is_empty() {
+                bail!("Package '{}' declares no variants", package.name);
+            }
+            config.variants.into_iter().map(Some).collect()
+        } else {
+            vec![args.variant.clone()]
+        };
+        for variant in variants {
+            let project_provider = project(variant);
+            let config = load(&project_provider)?;
+            let deploy = DefaultDeploy::new(project_provider, args.cargo_build.clone());
+
+            deploy.deploy(&config)?;
+        }
+    }
 
     Ok(())
 }
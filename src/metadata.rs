@@ -0,0 +1,157 @@
+#[cfg(test)]
+mod test;
+
+use crate::config::{CONFIG_FILE, PackageInfo};
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Workspace layout reported by `cargo metadata`.
+#[derive(Debug, Deserialize)]
+pub struct CargoMetadata {
+    pub packages: Vec<Package>,
+    pub workspace_root: PathBuf,
+    pub target_directory: PathBuf,
+    /// `[workspace.metadata]` of the workspace root.
+    #[serde(default)]
+    metadata: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Package {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub authors: Vec<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub manifest_path: PathBuf,
+    #[serde(default)]
+    metadata: Option<serde_json::Value>,
+}
+
+impl Package {
+    /// Whether the package has a module config, either in its metadata or
+    /// in a Magisk.toml next to its manifest.
+    pub fn has_config(&self) -> bool {
+        let in_metadata = self
+            .metadata
+            .as_ref()
+            .is_some_and(|value| value.get("magisk").is_some());
+        in_metadata
+            || self
+                .manifest_path
+                .parent()
+                .is_some_and(|value| value.join(CONFIG_FILE).is_file())
+    }
+}
+
+impl CargoMetadata {
+    /// Whether each package stages its module in a directory of its own:
+    /// when the workspace has several packages, or when the target
+    /// directory is outside the workspace and may be shared with other
+    /// projects.
+    pub fn separate_staging(&self) -> bool {
+        self.packages.len() > 1 || !self.target_directory.starts_with(&self.workspace_root)
+    }
+
+    /// Runs `cargo metadata` for the workspace of `manifest_path`.
+    pub fn load(manifest_path: &Path) -> Result<Self> {
+        let output = Command::new("cargo")
+            .args(["metadata", "--format-version", "1", "--no-deps"])
+            .arg("--manifest-path")
+            .arg(manifest_path)
+            .output()
+            .context("Failed to run cargo metadata")?;
+        if !output.status.success() {
+            bail!(
+                "cargo metadata failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Self::parse(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    pub fn parse(json: &str) -> Result<Self> {
+        serde_json::from_str(json).context("Invalid cargo metadata output")
+    }
+
+    /// Fields of `package` for the module config, with the workspace
+    /// `[workspace.metadata.magisk]` table as defaults.
+    pub fn package_info(&self, package: &Package) -> Result<PackageInfo> {
+        let defaults = match self.metadata.as_ref().and_then(|value| value.get("magisk")) {
+            Some(value) => Some(
+                serde_json::from_value(value.clone())
+                    .context("Invalid [workspace.metadata.magisk]: failed parse")?,
+            ),
+            None => None,
+        };
+        Ok(PackageInfo {
+            name: package.name.clone(),
+            version: package.version.clone(),
+            authors: package.authors.clone(),
+            description: package.description.clone(),
+            defaults,
+        })
+    }
+
+    /// Picks the packages to build into modules. Named packages are taken
+    /// as given, `workspace` takes every package with a module config.
+    /// Otherwise the package at `manifest_path` is built, or every package
+    /// with a module config when it is a virtual manifest.
+    pub fn select(
+        &self,
+        names: &[String],
+        workspace: bool,
+        manifest_path: &Path,
+    ) -> Result<Vec<&Package>> {
+        if !names.is_empty() {
+            let mut result: Vec<&Package> = vec![];
+            for name in names {
+                let package = self
+                    .packages
+                    .iter()
+                    .find(|value| &value.name == name)
+                    .with_context(|| format!("Package '{}' not found in workspace", name))?;
+                if !package.has_config() {
+                    bail!("Package '{}' has no [package.metadata.magisk]", name);
+                }
+                result.push(package);
+            }
+            return Ok(result);
+        }
+
+        let current = self
+            .packages
+            .iter()
+            .find(|value| same_path(&value.manifest_path, manifest_path));
+        if let Some(package) = current
+            && !workspace
+        {
+            return Ok(vec![package]);
+        }
+
+        let result: Vec<&Package> = self
+            .packages
+            .iter()
+            .filter(|value| value.has_config())
+            .collect();
+        if result.is_empty() {
+            bail!(
+                "No package in workspace '{}' has [package.metadata.magisk]",
+                self.workspace_root.display()
+            );
+        }
+        Ok(result)
+    }
+}
+
+fn same_path(left: &Path, right: &Path) -> bool {
+    match (left.canonicalize(), right.canonicalize()) {
+        (Ok(left), Ok(right)) => left == right,
+        _ => left == right,
+    }
+}
@@ -0,0 +1,161 @@
+use super::*;
+use rstest::{fixture, rstest};
+use std::fs;
+use tempfile::TempDir;
+
+#[rstest]
+#[case(&["daemon"], false, "Cargo.toml", &["daemon"])]
+#[case(&[], true, "daemon/Cargo.toml", &["app", "daemon"])]
+#[case(&[], false, "daemon/Cargo.toml", &["daemon"])]
+#[case(&[], false, "Cargo.toml", &["app", "daemon"])]
+fn select_ok(
+    workspace: TempDir,
+    #[case] names: &[&str],
+    #[case] all: bool,
+    #[case] manifest: &str,
+    #[case] expected: &[&str],
+) {
+    let metadata = metadata(workspace.path());
+    let names: Vec<String> = names.iter().map(|value| value.to_string()).collect();
+    let manifest_path = workspace.path().join(manifest);
+    let selected = metadata.select(&names, all, &manifest_path).unwrap();
+    let selected: Vec<&str> = selected.iter().map(|value| value.name.as_str()).collect();
+    assert_eq!(selected, expected);
+}
+
+#[rstest]
+fn parse_target_directory(workspace: TempDir) {
+    let metadata = metadata(workspace.path());
+    assert_eq!(
+        metadata.target_directory,
+        workspace.path().join("shared-target")
+    );
+}
+
+#[rstest]
+fn select_err(workspace: TempDir, #[values(&["shared"], &["missing"])] names: &[&str]) {
+    let metadata = metadata(workspace.path());
+    let names: Vec<String> = names.iter().map(|value| value.to_string()).collect();
+    let manifest_path = workspace.path().join("Cargo.toml");
+    let result = metadata.select(&names, false, &manifest_path);
+    assert!(
+        result.is_err(),
+        "result.is_err() not true. names: {:?}",
+        names
+    );
+}
+
+#[rstest]
+#[case(3, "shared-target", true)]
+#[case(1, "shared-target", false)]
+#[case(1, "/ci/target", true)]
+fn separate_staging(
+    workspace: TempDir,
+    #[case] packages: usize,
+    #[case] target: &str,
+    #[case] expected: bool,
+) {
+    let mut metadata = metadata(workspace.path());
+    metadata.packages.truncate(packages);
+    metadata.target_directory = workspace.path().join(target);
+    assert_eq!(metadata.separate_staging(), expected);
+}
+
+#[rstest]
+fn select_no_config(workspace: TempDir) {
+    let mut metadata = metadata(workspace.path());
+    metadata.packages.retain(|value| value.name == "shared");
+    let manifest_path = workspace.path().join("Cargo.toml");
+    assert!(metadata.select(&[], true, &manifest_path).is_err());
+}
+
+#[rstest]
+fn package_info(workspace: TempDir) {
+    let metadata = metadata(workspace.path());
+    let info = metadata.package_info(&metadata.packages[1]).unwrap();
+    assert_eq!(info.name, "daemon");
+    assert_eq!(info.version, "0.3.0");
+    assert_eq!(info.authors, ["Team"]);
+    assert_eq!(info.description.as_deref(), Some("A daemon"));
+    let expected: toml::Table =
+        toml::from_str("author = \"Maintainers\"\nrequires = { min_api = 30 }\n").unwrap();
+    assert_eq!(info.defaults, Some(expected));
+}
+
+#[rstest]
+fn load_resolves_inherited_fields() {
+    let project = tempfile::tempdir().unwrap();
+    let root = project.path();
+    fs::write(
+        root.join("Cargo.toml"),
+        "[workspace]\nmembers = [\"helper\"]\n\n\
+         [workspace.package]\nversion = \"2.0.1\"\nauthors = [\"Team\"]\n\n\
+         [workspace.metadata.magisk]\nauthor = \"Maintainers\"\n",
+    )
+    .unwrap();
+    fs::create_dir_all(root.join("helper/src")).unwrap();
+    fs::write(root.join("helper/src/main.rs"), "fn main() {}\n").unwrap();
+    let manifest_path = root.join("helper/Cargo.toml");
+    fs::write(
+        &manifest_path,
+        "[package]\nname = \"helper\"\nversion.workspace = true\n\
+         authors.workspace = true\nedition = \"2024\"\n",
+    )
+    .unwrap();
+
+    let metadata = CargoMetadata::load(&manifest_path).unwrap();
+    let info = metadata.package_info(&metadata.packages[0]).unwrap();
+    assert_eq!(info.version, "2.0.1");
+    assert_eq!(info.authors, ["Team"]);
+    let author = info.defaults.and_then(|value| value.get("author").cloned());
+    assert_eq!(author, Some(toml::Value::from("Maintainers")));
+}
+
+#[fixture]
+fn workspace() -> TempDir {
+    let workspace = tempfile::tempdir().unwrap();
+    for name in ["app", "daemon", "shared"] {
+        let dir = workspace.path().join(name);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("Cargo.toml"), "").unwrap();
+    }
+    fs::write(workspace.path().join("Cargo.toml"), "").unwrap();
+    fs::write(workspace.path().join("app").join(CONFIG_FILE), "").unwrap();
+    workspace
+}
+
+fn metadata(root: &Path) -> CargoMetadata {
+    let json = serde_json::json!({
+        "packages": [
+            {
+                "name": "app",
+                "version": "1.0.0",
+                "authors": [],
+                "description": null,
+                "manifest_path": root.join("app/Cargo.toml"),
+                "metadata": null
+            },
+            {
+                "name": "daemon",
+                "version": "0.3.0",
+                "authors": ["Team"],
+                "description": "A daemon",
+                "manifest_path": root.join("daemon/Cargo.toml"),
+                "metadata": { "magisk": { "id": "daemon" } }
+            },
+            {
+                "name": "shared",
+                "version": "1.0.0",
+                "authors": [],
+                "description": null,
+                "manifest_path": root.join("shared/Cargo.toml"),
+                "metadata": { "docs": {} }
+            }
+        ],
+        "workspace_members": [],
+        "workspace_root": root,
+        "metadata": { "magisk": { "author": "Maintainers", "requires": { "min_api": 30 } } },
+        "target_directory": root.join("shared-target")
+    });
+    CargoMetadata::parse(&json.to_string()).unwrap()
+}
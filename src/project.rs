@@ -1,5 +1,7 @@
 #[cfg(test)]
 mod test;
+#[cfg(test)]
+pub mod test_support;
 
 use std::{
     env,
@@ -11,37 +13,111 @@ use std::{
 use anyhow::{Ok, Result, bail};
 use clap::ValueEnum;
 
+/// Directory in the target path holding one staging directory per variant,
+/// so variants never share the staging directory of the plain module.
+pub const VARIANTS_DIR: &str = "magisk-variants";
+
 pub trait ProjectProvider: Debug {
+    fn get_manifest_path(&self) -> Result<PathBuf>;
     fn get_project_path(&self) -> Result<PathBuf>;
     fn get_target_path(&self) -> Result<PathBuf>;
     fn get_target(&self) -> &Target;
+    fn get_manager(&self) -> &Manager;
     fn is_release(&self) -> bool;
+
+    /// Name of the cargo package to build, when building from a workspace.
+    fn get_package(&self) -> Option<&str> {
+        None
+    }
+
+    /// Name of the module variant to build, see
+    /// `[package.metadata.magisk.variants]`.
+    fn get_variant(&self) -> Option<&str> {
+        None
+    }
+
+    /// Directory the module is assembled in.
+    fn get_staging_path(&self) -> Result<PathBuf> {
+        Ok(staging_path(self.get_target_path()?, self.get_variant()))
+    }
+
+    fn get_profile(&self) -> &'static str {
+        if self.is_release() {
+            "release"
+        } else {
+            "debug"
+        }
+    }
+}
+
+/// Staging directory in `target_path`: `magisk`, or
+/// `magisk-variants/<variant>` for a variant.
+fn staging_path(target_path: PathBuf, variant: Option<&str>) -> PathBuf {
+    let mut result = target_path;
+    match variant {
+        Some(value) => {
+            result.push(VARIANTS_DIR);
+            result.push(value);
+        }
+        None => result.push("magisk"),
+    }
+    result
 }
 
 pub trait ManifestProvider: Debug {
     fn find_manifest_path(&self) -> Result<PathBuf>;
 }
 
+/// Place of a package in its cargo workspace.
+#[derive(Debug, Clone)]
+pub struct PackageLayout {
+    pub name: String,
+    /// Cargo target directory, honouring `CARGO_TARGET_DIR` and
+    /// `build.target-dir`.
+    pub target_dir: PathBuf,
+    /// Stage the module in `magisk/<name>`, so modules of several packages
+    /// in one workspace, or of projects sharing a target directory, do not
+    /// overwrite each other.
+    pub separate_staging: bool,
+}
+
 #[derive(Debug, Clone)]
 pub struct DefaultProject {
     target: Target,
     release: bool,
+    manager: Manager,
     provider: Rc<dyn ManifestProvider>,
+    layout: Option<PackageLayout>,
+    variant: Option<String>,
 }
 
 impl DefaultProject {
-    pub fn new(target: Target, release: bool, provider: Rc<dyn ManifestProvider>) -> Self {
+    pub fn new(
+        target: Target,
+        release: bool,
+        manager: Manager,
+        provider: Rc<dyn ManifestProvider>,
+        layout: Option<PackageLayout>,
+        variant: Option<String>,
+    ) -> Self {
         Self {
             target,
             release,
+            manager,
             provider,
+            layout,
+            variant,
         }
     }
 }
 
 impl ProjectProvider for DefaultProject {
+    fn get_manifest_path(&self) -> Result<PathBuf> {
+        self.provider.find_manifest_path()
+    }
+
     fn get_project_path(&self) -> Result<PathBuf> {
-        let mut result = self.provider.find_manifest_path()?;
+        let mut result = self.get_manifest_path()?;
         if !result.pop() {
             bail!("Failed get Cargo.toml parent");
         }
@@ -49,11 +125,12 @@ impl ProjectProvider for DefaultProject {
     }
 
     fn get_target_path(&self) -> Result<PathBuf> {
-        let mut result = self.get_project_path()?;
-        result.push("target");
+        let mut result = match &self.layout {
+            Some(value) => value.target_dir.clone(),
+            None => self.get_project_path()?.join("target"),
+        };
         result.push(self.target.to_string());
-        let build_type = if self.release { "release" } else { "debug" };
-        result.push(build_type);
+        result.push(self.get_profile());
         Ok(result)
     }
 
@@ -61,9 +138,52 @@ impl ProjectProvider for DefaultProject {
         &self.target
     }
 
+    fn get_manager(&self) -> &Manager {
+        &self.manager
+    }
+
     fn is_release(&self) -> bool {
         self.release
     }
+
+    fn get_package(&self) -> Option<&str> {
+        self.layout.as_ref().map(|value| value.name.as_str())
+    }
+
+    fn get_variant(&self) -> Option<&str> {
+        self.variant.as_deref()
+    }
+
+    fn get_staging_path(&self) -> Result<PathBuf> {
+        let mut result = staging_path(self.get_target_path()?, self.get_variant());
+        if let Some(layout) = &self.layout
+            && layout.separate_staging
+        {
+            result.push(&layout.name);
+        }
+        Ok(result)
+    }
+}
+
+/// Manifest at a known path, e.g. a workspace member.
+#[derive(Clone, Debug)]
+pub struct PackageManifest {
+    manifest_path: PathBuf,
+}
+
+impl PackageManifest {
+    pub fn new(manifest_path: PathBuf) -> Self {
+        Self { manifest_path }
+    }
+}
+
+impl ManifestProvider for PackageManifest {
+    fn find_manifest_path(&self) -> Result<PathBuf> {
+        if !self.manifest_path.exists() {
+            bail!("Invalid manifest path: file not exists");
+        }
+        Ok(self.manifest_path.clone())
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -103,6 +223,27 @@ pub enum Target {
     X86,
 }
 
+impl Target {
+    pub const ABIS: [&str; 4] = ["arm64-v8a", "armeabi-v7a", "x86_64", "x86"];
+
+    /// Name of the system library directory for this target.
+    pub fn libdir(&self) -> &'static str {
+        match self {
+            Target::Arm64V8a | Target::X86_64 => "lib64",
+            Target::ArmeabiV7a | Target::X86 => "lib",
+        }
+    }
+
+    pub fn abi(&self) -> &'static str {
+        match self {
+            Target::Arm64V8a => "arm64-v8a",
+            Target::ArmeabiV7a => "armeabi-v7a",
+            Target::X86_64 => "x86_64",
+            Target::X86 => "x86",
+        }
+    }
+}
+
 impl Display for Target {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self.to_possible_value() {
@@ -111,3 +252,11 @@ impl Display for Target {
         }
     }
 }
+
+#[derive(Debug, PartialEq, Clone, ValueEnum)]
+pub enum Manager {
+    #[value(name = "magisk")]
+    Magisk,
+    #[value(name = "kernelsu", alias = "ksu")]
+    KernelSu,
+}
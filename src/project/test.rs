@@ -7,7 +7,14 @@ fn default_project_get_project_path(
     manifest_provider: Rc<dyn ManifestProvider>,
     #[case] expected: PathBuf,
 ) {
-    let project = DefaultProject::new(Target::Arm64V8a, true, manifest_provider);
+    let project = DefaultProject::new(
+        Target::Arm64V8a,
+        true,
+        Manager::Magisk,
+        manifest_provider,
+        None,
+        None,
+    );
     let project_path = project.get_project_path().unwrap();
     assert_eq!(project_path, expected);
 }
@@ -19,11 +26,63 @@ fn default_project_get_target_path(
     manifest_provider: Rc<dyn ManifestProvider>,
 ) {
     let expected = get_expected_path(&target, release);
-    let project = DefaultProject::new(target, release, manifest_provider);
+    let project = DefaultProject::new(
+        target,
+        release,
+        Manager::Magisk,
+        manifest_provider,
+        None,
+        None,
+    );
     let target_path = project.get_target_path().unwrap();
     assert_eq!(target_path, expected);
 }
 
+#[rstest]
+#[case(false, None, "/cache/cargo/aarch64-linux-android/debug/magisk")]
+#[case(true, None, "/cache/cargo/aarch64-linux-android/debug/magisk/daemon")]
+#[case(
+    false,
+    Some("lite"),
+    "/cache/cargo/aarch64-linux-android/debug/magisk-variants/lite"
+)]
+#[case(
+    true,
+    Some("lite"),
+    "/cache/cargo/aarch64-linux-android/debug/magisk-variants/lite/daemon"
+)]
+fn default_project_workspace_layout(
+    #[case] separate_staging: bool,
+    #[case] variant: Option<&str>,
+    #[case] expected_staging: &str,
+) {
+    let manifest_provider: Rc<dyn ManifestProvider> = Rc::new(PackageManifest::new(PathBuf::from(
+        "/workspace/crates/daemon/Cargo.toml",
+    )));
+    let layout = PackageLayout {
+        name: "daemon".to_string(),
+        target_dir: PathBuf::from("/cache/cargo"),
+        separate_staging,
+    };
+    let project = DefaultProject::new(
+        Target::Arm64V8a,
+        false,
+        Manager::Magisk,
+        manifest_provider,
+        Some(layout),
+        variant.map(str::to_string),
+    );
+    let target_path = project.get_target_path().unwrap();
+    assert_eq!(
+        target_path,
+        PathBuf::from("/cache/cargo/aarch64-linux-android/debug")
+    );
+    let staging_path = project.get_staging_path().unwrap();
+    assert_eq!(staging_path, PathBuf::from(expected_staging));
+    assert_eq!(project.get_package(), Some("daemon"));
+    assert_eq!(project.get_variant(), variant);
+}
+
 fn get_expected_path(target: &Target, release: bool) -> PathBuf {
     let expected_str = match (target, release) {
         (&Target::Arm64V8a, true) => "/workspace/target/aarch64-linux-android/release",
@@ -38,6 +97,22 @@ fn get_expected_path(target: &Target, release: bool) -> PathBuf {
     PathBuf::from(expected_str)
 }
 
+#[rstest]
+#[case(Target::Arm64V8a, "arm64-v8a")]
+#[case(Target::ArmeabiV7a, "armeabi-v7a")]
+#[case(Target::X86_64, "x86_64")]
+#[case(Target::X86, "x86")]
+fn target_abi(#[case] target: Target, #[case] expected: &str) {
+    assert_eq!(target.abi(), expected);
+    let libdir = if expected.contains("64") {
+        "lib64"
+    } else {
+        "lib"
+    };
+    assert_eq!(target.libdir(), libdir);
+    assert!(Target::ABIS.contains(&expected));
+}
+
 #[fixture]
 fn manifest_provider() -> Rc<dyn ManifestProvider> {
     Rc::new(MockManifest {})
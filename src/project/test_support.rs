@@ -0,0 +1,53 @@
+use super::{Manager, ProjectProvider, Target};
+use anyhow::Result;
+use std::path::PathBuf;
+
+/// Project with fixed paths, shared by the tests of other modules.
+#[derive(Debug)]
+pub struct MockProject {
+    pub project_path: PathBuf,
+    pub target_path: PathBuf,
+    pub package: Option<String>,
+    pub release: bool,
+}
+
+impl Default for MockProject {
+    fn default() -> Self {
+        Self {
+            project_path: PathBuf::from("/workspace"),
+            target_path: PathBuf::from("/workspace/target/arch/build_type"),
+            package: None,
+            release: false,
+        }
+    }
+}
+
+impl ProjectProvider for MockProject {
+    fn get_manifest_path(&self) -> Result<PathBuf> {
+        Ok(self.project_path.join("Cargo.toml"))
+    }
+
+    fn get_project_path(&self) -> Result<PathBuf> {
+        Ok(self.project_path.clone())
+    }
+
+    fn get_target_path(&self) -> Result<PathBuf> {
+        Ok(self.target_path.clone())
+    }
+
+    fn get_target(&self) -> &Target {
+        &Target::Arm64V8a
+    }
+
+    fn get_manager(&self) -> &Manager {
+        &Manager::Magisk
+    }
+
+    fn is_release(&self) -> bool {
+        self.release
+    }
+
+    fn get_package(&self) -> Option<&str> {
+        self.package.as_deref()
+    }
+}
@@ -0,0 +1,598 @@
+#[cfg(test)]
+mod test;
+
+use crate::config::{Config, DataDir, InstallOption, Requires, ScriptEntry, Service, Stage};
+use anyhow::{Context, Result};
+use std::{collections::BTreeMap, fmt::Write, fs, path::Path};
+
+pub const BIN_DIR: &str = "bin";
+
+const WAIT_FOR_BOOT: &str = "\
+wait_for_boot() {
+    until [ \"$(getprop sys.boot_completed)\" = \"1\" ]; do
+        sleep 1
+    done
+}";
+
+const SET_DESCRIPTION: &str = "\
+set_description() {
+    sed -i '/^description=/d' \"$MODDIR/module.prop\"
+    [ -n \"$1\" ] && echo \"description=$1\" >>\"$MODDIR/module.prop\"
+}";
+
+const CHOOSE: &str = r#"choose() {
+    ui_print "- $1"
+    if [ "$2" = 0 ]; then default=Yes; else default=No; fi
+    ui_print "  Vol+ = Yes, Vol- = No (default $default in $3s)"
+    deadline=$(($(date +%s) + $3))
+    while true; do
+        event=$(timeout 1 getevent -lqc 1 2>/dev/null)
+        case "$event" in
+            *KEY_VOLUMEUP*DOWN*) return 0 ;;
+            *KEY_VOLUMEDOWN*DOWN*) return 1 ;;
+        esac
+        [ "$(date +%s)" -lt "$deadline" ] || break
+    done
+    return "$2"
+}"#;
+
+const SERVICE_CTL: &str = r#"is_running() {
+    [ -f "$DATADIR/$1.supervisor.pid" ] && kill -0 "$(cat "$DATADIR/$1.supervisor.pid")" 2>/dev/null
+}
+
+launch_as() {
+    fifo="$2.fifo"
+    rm -f "$fifo"
+    mkfifo "$fifo" || return 1
+    su "$1" -c "echo \$\$; exec $3 >&2" >"$fifo" &
+    client=$!
+    read -r pid <"$fifo"
+    rm -f "$fifo"
+    [ -n "$pid" ] && echo "$pid" >"$2"
+    wait "$client"
+}
+
+supervise() {
+    delay=$BACKOFF
+    while true; do
+        started=$(date +%s)
+        launch >>"$LOG" 2>&1 &
+        echo $! >"$DATADIR/$1.pid"
+        wait $!
+        status=$?
+        rm -f "$DATADIR/$1.pid" "$DATADIR/$1.user.pid"
+        case "$POLICY" in
+            never) break ;;
+            on-failure) [ "$status" -eq 0 ] && break ;;
+        esac
+        if [ $(($(date +%s) - started)) -ge "$MAX_BACKOFF" ]; then
+            delay=$BACKOFF
+        fi
+        echo "$1 exited with status $status, restarting in ${delay}s" >>"$LOG"
+        sleep "$delay"
+        delay=$((delay * 2))
+        [ "$delay" -gt "$MAX_BACKOFF" ] && delay=$MAX_BACKOFF
+    done
+    rm -f "$DATADIR/$1.supervisor.pid"
+}
+
+start() {
+    configure "$1" || return 1
+    if is_running "$1"; then
+        echo "$1 is already running"
+        return 0
+    fi
+    mkdir -p "$DATADIR"
+    supervise "$1" </dev/null >/dev/null 2>&1 &
+    echo $! >"$DATADIR/$1.supervisor.pid"
+}
+
+stop() {
+    configure "$1" || return 1
+    for file in "$DATADIR/$1.supervisor.pid" "$DATADIR/$1.user.pid" "$DATADIR/$1.pid"; do
+        [ -f "$file" ] && kill "$(cat "$file")" 2>/dev/null
+        rm -f "$file"
+    done
+}
+
+status() {
+    configure "$1" || return 1
+    if is_running "$1"; then
+        echo "$1 is running"
+    else
+        echo "$1 is stopped"
+        return 1
+    fi
+}
+
+action=$1
+[ $# -gt 0 ] && shift
+[ $# -eq 0 ] && set -- $SERVICES
+result=0
+for name in "$@"; do
+    case "$action" in
+        start | stop | status) "$action" "$name" || result=1 ;;
+        restart) stop "$name" && start "$name" || result=1 ;;
+        *)
+            echo "Usage: ${0##*/} start|stop|restart|status [service...]" >&2
+            exit 1
+            ;;
+    esac
+done
+exit $result"#;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum ScriptKind {
+    Customize,
+    PostFsData,
+    Service,
+    Uninstall,
+    ServiceCtl,
+}
+
+impl ScriptKind {
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            ScriptKind::Customize => "customize.sh",
+            ScriptKind::PostFsData => "post-fs-data.sh",
+            ScriptKind::Service => "service.sh",
+            ScriptKind::Uninstall => "uninstall.sh",
+            ScriptKind::ServiceCtl => "service-ctl.sh",
+        }
+    }
+}
+
+impl From<Stage> for ScriptKind {
+    fn from(stage: Stage) -> Self {
+        match stage {
+            Stage::PostFsData => ScriptKind::PostFsData,
+            Stage::Service => ScriptKind::Service,
+            Stage::Uninstall => ScriptKind::Uninstall,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Script {
+    helpers: Vec<String>,
+    sections: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct ScriptSet {
+    scripts: BTreeMap<ScriptKind, Script>,
+}
+
+impl ScriptSet {
+    pub fn from_config(config: &Config) -> Self {
+        let mut result = Self::default();
+        result.push_requires(&config.requires);
+        if let Some(max_boots) = config.bootloop_guard {
+            result.push_bootloop_guard(
+                max_boots,
+                &config.data_path(),
+                config.module_prop.description.as_deref(),
+            );
+        }
+        if let Some(data_dir) = &config.data_dir {
+            let installed_prop = format!("/data/adb/modules/{}/module.prop", config.module_prop.id);
+            result.push_data_dir(data_dir, &installed_prop);
+        }
+        if !config.options.is_empty() {
+            result.push_options(&config.options);
+        }
+        result.push_overlays(&config.remove, &config.replace);
+        for entry in &config.scripts {
+            result.push_entry(entry);
+        }
+        if !config.services.is_empty() {
+            result.push_services(&config.services, &config.data_path());
+        }
+        for binary in config.binaries() {
+            result.push(
+                ScriptKind::Customize,
+                format!("set_perm \"$MODPATH/{}/{}\" 0 0 0755", BIN_DIR, binary),
+            );
+        }
+        result
+    }
+
+    pub fn push(&mut self, kind: ScriptKind, section: String) {
+        self.scripts.entry(kind).or_default().sections.push(section);
+    }
+
+    pub fn push_helper(&mut self, kind: ScriptKind, helper: String) {
+        let script = self.scripts.entry(kind).or_default();
+        if !script.helpers.contains(&helper) {
+            script.helpers.push(helper);
+        }
+    }
+
+    /// Writes every non-empty script into `staging_path`. A script that
+    /// already exists there (copied as an asset) is kept and appended to
+    /// the generated content.
+    pub fn write(&self, staging_path: &Path) -> Result<()> {
+        for (kind, script) in &self.scripts {
+            let path = staging_path.join(kind.file_name());
+            let user = match path.exists() {
+                true => Some(
+                    fs::read_to_string(&path)
+                        .with_context(|| format!("Failed read '{}'", path.display()))?,
+                ),
+                false => None,
+            };
+            fs::write(&path, Self::render(*kind, script, user.as_deref()))?;
+        }
+        Ok(())
+    }
+
+    fn render(kind: ScriptKind, script: &Script, user: Option<&str>) -> String {
+        let mut result = String::from("#!/system/bin/sh\n# Generated by cargo-magisk\n");
+        if kind != ScriptKind::Customize {
+            result.push_str("MODDIR=${0%/*}\n");
+        }
+        for helper in &script.helpers {
+            result.push('\n');
+            result.push_str(helper);
+            result.push('\n');
+        }
+        for section in &script.sections {
+            result.push('\n');
+            result.push_str(section);
+            if !section.ends_with('\n') {
+                result.push('\n');
+            }
+        }
+        if let Some(user) = user {
+            let user = match user.strip_prefix("#!") {
+                Some(value) => value.split_once('\n').map_or("", |(_, rest)| rest),
+                None => user,
+            };
+            result.push('\n');
+            result.push_str(user);
+        }
+        result
+    }
+
+    fn push_entry(&mut self, entry: &ScriptEntry) {
+        let kind = ScriptKind::from(entry.stage);
+        let mut command = format!("\"$MODDIR/{}/{}\"", BIN_DIR, entry.binary);
+        for arg in &entry.args {
+            command.push(' ');
+            command.push_str(&quote(arg));
+        }
+        match &entry.log {
+            Some(value) => write!(command, " >>{} 2>&1", quote(value)).unwrap(),
+            None => command.push_str(" >/dev/null 2>&1"),
+        }
+
+        let section = if entry.wait_for_boot {
+            self.push_helper(kind, WAIT_FOR_BOOT.to_string());
+            format!("(\n    wait_for_boot\n    exec {}\n) &", command)
+        } else if entry.stage == Stage::Uninstall {
+            command
+        } else {
+            format!("{} &", command)
+        };
+        self.push(kind, section);
+    }
+
+    /// Aborts the installation when the device does not meet `requires`.
+    fn push_requires(&mut self, requires: &Requires) {
+        let kind = ScriptKind::Customize;
+        if let Some(min_api) = requires.min_api {
+            self.push(
+                kind,
+                format!(
+                    "[ \"$API\" -ge {0} ] || abort \"! Android API {0} or newer is required (found $API)\"",
+                    min_api
+                ),
+            );
+        }
+
+        let manager_checks = [
+            ("=", "KSU_VER_CODE", "KernelSU", requires.min_ksu),
+            ("!=", "MAGISK_VER_CODE", "Magisk", requires.min_magisk),
+        ];
+        for (operator, variable, manager, min_version) in manager_checks {
+            let Some(min_version) = min_version else {
+                continue;
+            };
+            self.push(
+                kind,
+                format!(
+                    "if [ \"$KSU\" {operator} true ] && [ \"${variable}\" -lt {min_version} ]; then\n    \
+                         abort \"! {manager} version code {min_version} or newer is required (found ${variable})\"\n\
+                     fi"
+                ),
+            );
+        }
+
+        // A device runs every ABI of its abilist, e.g. armeabi-v7a builds
+        // on arm64 devices.
+        let abis: Vec<String> = requires.abis.iter().map(|value| quote(value)).collect();
+        self.push(
+            kind,
+            format!(
+                "abilist=$(getprop ro.product.cpu.abilist)\n\
+                 [ -n \"$abilist\" ] || abilist=$(getprop ro.product.cpu.abi)\n\
+                 abi_supported=false\n\
+                 for abi in $(echo \"$abilist\" | tr ',' ' '); do\n    \
+                     case \"$abi\" in\n        \
+                         {}) abi_supported=true ;;\n    \
+                     esac\n\
+                 done\n\
+                 [ \"$abi_supported\" = true ] ||\n    \
+                     abort \"! Unsupported ABI: $abilist (supported: {})\"",
+                abis.join(" | "),
+                requires.abis.join(", ")
+            ),
+        );
+
+        for id in &requires.modules {
+            self.push(
+                kind,
+                format!(
+                    "if [ ! -d /data/adb/modules/{id} ] || [ -f /data/adb/modules/{id}/disable ] ||\n    \
+                         [ -f /data/adb/modules/{id}/remove ]; then\n    \
+                         abort \"! Module {id} is required\"\n\
+                     fi"
+                ),
+            );
+        }
+        for id in &requires.conflicts {
+            self.push(
+                kind,
+                format!(
+                    "if [ -d /data/adb/modules/{id} ] && [ ! -f /data/adb/modules/{id}/remove ]; then\n    \
+                         abort \"! Module {id} conflicts with this module, remove it first\"\n\
+                     fi"
+                ),
+            );
+        }
+    }
+
+    /// Creates the data directory on install and runs every migration newer
+    /// than the `versionCode` found in `installed_prop`.
+    fn push_data_dir(&mut self, data_dir: &DataDir, installed_prop: &str) {
+        let kind = ScriptKind::Customize;
+        self.push_helper(kind, format!("DATADIR={}", quote(&data_dir.path)));
+        self.push(kind, "mkdir -p \"$DATADIR\"".to_string());
+        if !data_dir.migrations.is_empty() {
+            let codes: Vec<String> = data_dir
+                .migrations
+                .iter()
+                .map(|value| value.version_code.to_string())
+                .collect();
+            self.push(
+                kind,
+                format!(
+                    "old_version_code=$(sed -n 's/^versionCode=//p' {} 2>/dev/null)\n\
+                     if [ -n \"$old_version_code\" ]; then\n    \
+                         for migration in {}; do\n        \
+                             [ \"$old_version_code\" -lt \"$migration\" ] || continue\n        \
+                             ui_print \"- Running migration $migration\"\n        \
+                             DATADIR=\"$DATADIR\" sh \"$MODPATH/{dir}/$migration.sh\" \"$old_version_code\" ||\n            \
+                                 abort \"! Migration $migration failed\"\n    \
+                         done\n\
+                     fi\n\
+                     rm -rf \"$MODPATH/{dir}\"",
+                    quote(installed_prop),
+                    codes.join(" "),
+                    dir = DataDir::MIGRATIONS,
+                ),
+            );
+        }
+
+        if data_dir.clean_on_uninstall {
+            self.push_helper(
+                ScriptKind::Uninstall,
+                format!("DATADIR={}", quote(&data_dir.path)),
+            );
+            self.push(ScriptKind::Uninstall, "rm -rf \"$DATADIR\"".to_string());
+        }
+    }
+
+    /// Asks for every option with the volume keys and moves the files of
+    /// the chosen ones from `options/<name>/` into the module.
+    fn push_options(&mut self, options: &[InstallOption]) {
+        let kind = ScriptKind::Customize;
+        self.push_helper(kind, CHOOSE.to_string());
+        for option in options {
+            let default = if option.default { 0 } else { 1 };
+            self.push(
+                kind,
+                format!(
+                    "if choose {} {} {}; then\n    \
+                         ui_print \"  Installing {name}\"\n    \
+                         [ -d \"$MODPATH/{dir}/{name}\" ] && cp -af \"$MODPATH/{dir}/{name}/.\" \"$MODPATH/\"\n\
+                     fi",
+                    quote(&option.prompt),
+                    default,
+                    option.timeout,
+                    name = option.name,
+                    dir = InstallOption::DIR,
+                ),
+            );
+        }
+        self.push(kind, format!("rm -rf \"$MODPATH/{}\"", InstallOption::DIR));
+    }
+
+    /// Sets the installer `REPLACE` list. KernelSU removes `remove` paths with
+    /// whiteouts from its `REMOVE` list, Magisk gets empty overlays instead.
+    fn push_overlays(&mut self, remove: &[String], replace: &[String]) {
+        let kind = ScriptKind::Customize;
+        if !replace.is_empty() {
+            self.push(kind, format!("REPLACE={}", quote(&replace.join(" "))));
+        }
+        if !remove.is_empty() {
+            let targets: Vec<String> = remove.iter().map(|value| quote(value)).collect();
+            self.push(
+                kind,
+                format!(
+                    "if [ \"$KSU\" = true ]; then\n    \
+                         REMOVE={list}\n\
+                     else\n    \
+                         for target in {targets}; do\n        \
+                             if [ -d \"$target\" ]; then\n            \
+                                 mkdir -p \"$MODPATH$target\"\n            \
+                                 touch \"$MODPATH$target/.replace\"\n        \
+                             else\n            \
+                                 mkdir -p \"$MODPATH${{target%/*}}\"\n            \
+                                 touch \"$MODPATH$target\"\n        \
+                             fi\n    \
+                         done\n\
+                     fi",
+                    list = quote(&remove.join(" ")),
+                    targets = targets.join(" "),
+                ),
+            );
+        }
+    }
+
+    /// Counts boots that never reached `sys.boot_completed` and disables the
+    /// module once `max_boots` of them happened in a row.
+    fn push_bootloop_guard(&mut self, max_boots: u32, data_path: &str, description: Option<&str>) {
+        let message = match description {
+            Some(value) => format!(
+                "[Disabled by bootloop guard after {} unfinished boots] {}",
+                max_boots, value
+            ),
+            None => format!(
+                "[Disabled by bootloop guard after {} unfinished boots]",
+                max_boots
+            ),
+        };
+        for kind in [ScriptKind::PostFsData, ScriptKind::Service] {
+            self.push_helper(kind, format!("DATADIR={}", quote(data_path)));
+            self.push_helper(kind, SET_DESCRIPTION.to_string());
+        }
+        self.push(
+            ScriptKind::PostFsData,
+            format!(
+                "mkdir -p \"$DATADIR\"\n\
+                 boot_count=$(cat \"$DATADIR/boot_count\" 2>/dev/null || echo 0)\n\
+                 if [ \"$boot_count\" -ge {} ]; then\n    \
+                     rm -f \"$DATADIR/boot_count\"\n    \
+                     touch \"$MODDIR/disable\"\n    \
+                     set_description {}\n    \
+                     exit 0\n\
+                 fi\n\
+                 echo $((boot_count + 1)) >\"$DATADIR/boot_count\"",
+                max_boots,
+                quote(&message)
+            ),
+        );
+
+        self.push_helper(ScriptKind::Service, WAIT_FOR_BOOT.to_string());
+        self.push(
+            ScriptKind::Service,
+            format!(
+                "[ -f \"$MODDIR/disable\" ] && exit 0\n\
+                 (\n    \
+                     wait_for_boot\n    \
+                     rm -f \"$DATADIR/boot_count\"\n    \
+                     set_description {}\n\
+                 ) &",
+                quote(description.unwrap_or_default())
+            ),
+        );
+    }
+
+    fn push_services(&mut self, services: &[Service], data_path: &str) {
+        let kind = ScriptKind::ServiceCtl;
+        let names: Vec<&str> = services.iter().map(|value| value.name.as_str()).collect();
+        self.push_helper(kind, format!("DATADIR={}", quote(data_path)));
+        self.push(kind, format!("SERVICES={}", quote(&names.join(" "))));
+
+        let mut configure = String::from("configure() {\n    case \"$1\" in\n");
+        for service in services {
+            let log = match &service.log {
+                Some(value) => value.clone(),
+                None => format!("{}/{}.log", data_path, service.name),
+            };
+            let launch = match &service.user {
+                // `su` hands the command to a process that is not our child,
+                // so the shell it starts reports its own pid for `stop`.
+                Some(user) => {
+                    let mut command = format!("\\\"$MODDIR/{}/{}\\\"", BIN_DIR, service.binary);
+                    for arg in &service.args {
+                        command.push(' ');
+                        command.push_str(&escape_double(&quote(arg)));
+                    }
+                    format!(
+                        "launch_as {} \"$DATADIR/{}.user.pid\" \"{}\"",
+                        quote(user),
+                        service.name,
+                        command
+                    )
+                }
+                None => {
+                    let mut command = format!("exec \"$MODDIR/{}/{}\"", BIN_DIR, service.binary);
+                    for arg in &service.args {
+                        command.push(' ');
+                        command.push_str(&quote(arg));
+                    }
+                    command
+                }
+            };
+            writeln!(configure, "        {})", quote(&service.name)).unwrap();
+            writeln!(configure, "            POLICY={}", service.restart.name()).unwrap();
+            writeln!(configure, "            BACKOFF={}", service.backoff).unwrap();
+            writeln!(configure, "            MAX_BACKOFF={}", service.max_backoff).unwrap();
+            writeln!(configure, "            LOG={}", quote(&log)).unwrap();
+            writeln!(configure, "            launch() {{ {}; }}", launch).unwrap();
+            configure.push_str("            ;;\n");
+        }
+        configure.push_str("        *)\n");
+        configure.push_str("            echo \"Unknown service: $1\" >&2\n");
+        configure.push_str("            return 1\n");
+        configure.push_str("            ;;\n");
+        configure.push_str("    esac\n}");
+        self.push(kind, configure);
+        self.push(kind, SERVICE_CTL.to_string());
+
+        // Pid files outlive a reboot in the data directory, and a recycled
+        // pid would make `start` report the service as already running.
+        self.push_helper(
+            ScriptKind::PostFsData,
+            format!("DATADIR={}", quote(data_path)),
+        );
+        self.push(
+            ScriptKind::PostFsData,
+            "rm -f \"$DATADIR\"/*.pid".to_string(),
+        );
+
+        for service in services {
+            self.push(
+                ScriptKind::Service,
+                format!(
+                    "sh \"$MODDIR/{}\" start {}",
+                    kind.file_name(),
+                    quote(&service.name)
+                ),
+            );
+        }
+        self.push(
+            ScriptKind::Customize,
+            format!("set_perm \"$MODPATH/{}\" 0 0 0755", kind.file_name()),
+        );
+    }
+}
+
+/// Escapes `value` for use inside a double-quoted POSIX shell string.
+fn escape_double(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '"' | '$' | '`') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Quotes `value` for use as a single POSIX shell word.
+pub fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
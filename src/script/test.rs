@@ -0,0 +1,592 @@
+use super::*;
+use crate::config::{RestartPolicy, VersionCode};
+use crate::project::Target;
+use rstest::rstest;
+use std::{os::unix::fs::PermissionsExt, process::Command, thread, time::Duration};
+
+#[rstest]
+#[case("plain", "'plain'")]
+#[case("with space", "'with space'")]
+#[case("it's", "'it'\\''s'")]
+fn quote_value(#[case] value: &str, #[case] expected: &str) {
+    assert_eq!(quote(value), expected);
+}
+
+#[rstest]
+fn script_set_push_entry() {
+    let mut scripts = ScriptSet::default();
+    scripts.push_entry(&entry("daemon", Stage::Service, true, Some("/data/d.log")));
+    scripts.push_entry(&entry("daemon", Stage::PostFsData, false, None));
+    scripts.push_entry(&entry("cleanup", Stage::Uninstall, false, None));
+
+    let service = render(&scripts, ScriptKind::Service, None);
+    assert_eq!(
+        service,
+        "#!/system/bin/sh\n\
+         # Generated by cargo-magisk\n\
+         MODDIR=${0%/*}\n\
+         \n\
+         wait_for_boot() {\n    \
+             until [ \"$(getprop sys.boot_completed)\" = \"1\" ]; do\n        \
+                 sleep 1\n    \
+             done\n\
+         }\n\
+         \n\
+         (\n    \
+             wait_for_boot\n    \
+             exec \"$MODDIR/bin/daemon\" '--flag' >>'/data/d.log' 2>&1\n\
+         ) &\n"
+    );
+
+    let post_fs_data = render(&scripts, ScriptKind::PostFsData, None);
+    assert!(post_fs_data.ends_with("\"$MODDIR/bin/daemon\" '--flag' >/dev/null 2>&1 &\n"));
+
+    let uninstall = render(&scripts, ScriptKind::Uninstall, None);
+    assert!(uninstall.ends_with("\"$MODDIR/bin/cleanup\" '--flag' >/dev/null 2>&1\n"));
+}
+
+#[rstest]
+fn script_set_write_appends_user_script() {
+    let staging = tempfile::tempdir().unwrap();
+    fs::write(
+        staging.path().join("service.sh"),
+        "#!/system/bin/sh\necho user\n",
+    )
+    .unwrap();
+
+    let mut scripts = ScriptSet::default();
+    scripts.push(ScriptKind::Service, "echo generated".to_string());
+    scripts.write(staging.path()).unwrap();
+
+    let service = fs::read_to_string(staging.path().join("service.sh")).unwrap();
+    assert_eq!(
+        service,
+        "#!/system/bin/sh\n\
+         # Generated by cargo-magisk\n\
+         MODDIR=${0%/*}\n\
+         \n\
+         echo generated\n\
+         \n\
+         echo user\n"
+    );
+    assert!(!staging.path().join("uninstall.sh").exists());
+}
+
+#[rstest]
+fn script_set_push_services() {
+    let mut scripts = ScriptSet::default();
+    let mut daemon = service("daemon");
+    daemon.user = Some("shell".to_string());
+    scripts.push_services(&[daemon, service("other")], "/data/adb/a_module");
+
+    let service_sh = render(&scripts, ScriptKind::Service, None);
+    assert!(service_sh.contains("sh \"$MODDIR/service-ctl.sh\" start 'daemon'\n"));
+    assert!(service_sh.contains("sh \"$MODDIR/service-ctl.sh\" start 'other'\n"));
+
+    let ctl = render(&scripts, ScriptKind::ServiceCtl, None);
+    assert!(ctl.contains("DATADIR='/data/adb/a_module'\n\nSERVICES='daemon other'\n"));
+    assert!(ctl.contains("LOG='/data/adb/a_module/daemon.log'\n"));
+    assert!(ctl.contains(
+        "launch() { launch_as 'shell' \"$DATADIR/daemon.user.pid\" \"\\\"$MODDIR/bin/daemon\\\" '--flag'\"; }\n"
+    ));
+    assert!(ctl.contains("launch() { exec \"$MODDIR/bin/other\" '--flag'; }\n"));
+}
+
+#[rstest]
+fn service_ctl_start_stop() {
+    let sandbox = tempfile::tempdir().unwrap();
+    let data_path = sandbox.path().join("data");
+    let bin_path = sandbox.path().join(BIN_DIR);
+    fs::create_dir_all(&bin_path).unwrap();
+    let daemon_path = bin_path.join("daemon");
+    fs::write(&daemon_path, "#!/bin/sh\nsleep 30\n").unwrap();
+    fs::set_permissions(&daemon_path, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut scripts = ScriptSet::default();
+    scripts.push_services(&[service("daemon")], data_path.to_str().unwrap());
+    scripts.write(sandbox.path()).unwrap();
+    let ctl = sandbox.path().join(ScriptKind::ServiceCtl.file_name());
+    let run = |action: &str| Command::new("sh").arg(&ctl).arg(action).status().unwrap();
+
+    assert!(run("start").success());
+    let pid_path = data_path.join("daemon.pid");
+    for _ in 0..50 {
+        if pid_path.exists() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    assert!(pid_path.exists());
+    assert!(run("status").success());
+
+    assert!(run("stop").success());
+    assert!(!run("status").success());
+    assert!(!pid_path.exists());
+    assert!(!data_path.join("daemon.supervisor.pid").exists());
+}
+
+#[rstest]
+fn service_ctl_user_start_stop() {
+    let sandbox = tempfile::tempdir().unwrap();
+    let data_path = sandbox.path().join("data");
+    let bin_path = sandbox.path().join(BIN_DIR);
+    fs::create_dir_all(&bin_path).unwrap();
+    let args_path = sandbox.path().join("args");
+    let daemon_path = bin_path.join("daemon");
+    fs::write(
+        &daemon_path,
+        format!(
+            "#!/bin/sh\nprintf '%s\\n' \"$@\" >{}\nexec sleep 30\n",
+            quote(args_path.to_str().unwrap())
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&daemon_path, fs::Permissions::from_mode(0o755)).unwrap();
+    // Stands in for `su <user> -c <command>`: the command runs in a fresh
+    // shell that does not see MODDIR.
+    let stub_path = sandbox.path().join("stub");
+    fs::create_dir_all(&stub_path).unwrap();
+    let su_path = stub_path.join("su");
+    fs::write(
+        &su_path,
+        "#!/bin/sh\n[ \"$2\" = -c ] || exit 1\nexec env -u MODDIR sh -c \"$3\"\n",
+    )
+    .unwrap();
+    fs::set_permissions(&su_path, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut daemon = service("daemon");
+    daemon.user = Some("shell".to_string());
+    daemon.args.push("a \"$b\" `c` \\d".to_string());
+    let mut scripts = ScriptSet::default();
+    scripts.push_services(&[daemon], data_path.to_str().unwrap());
+    scripts.write(sandbox.path()).unwrap();
+    let ctl = sandbox.path().join(ScriptKind::ServiceCtl.file_name());
+    let path = format!(
+        "{}:{}",
+        stub_path.display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    let run = |action: &str| {
+        Command::new("sh")
+            .arg(&ctl)
+            .arg(action)
+            .env("PATH", &path)
+            .status()
+            .unwrap()
+    };
+
+    assert!(run("start").success());
+    let user_pid_path = data_path.join("daemon.user.pid");
+    for _ in 0..50 {
+        if user_pid_path.exists() && args_path.exists() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    assert_eq!(
+        fs::read_to_string(&args_path).unwrap(),
+        "--flag\na \"$b\" `c` \\d\n"
+    );
+    let pid = fs::read_to_string(&user_pid_path).unwrap();
+    let proc_path = Path::new("/proc").join(pid.trim());
+    // A zombie keeps its /proc entry until it is reaped.
+    let alive = || {
+        fs::read_to_string(proc_path.join("stat")).is_ok_and(|stat| {
+            !stat
+                .rsplit(')')
+                .next()
+                .unwrap()
+                .trim_start()
+                .starts_with('Z')
+        })
+    };
+    assert!(alive());
+
+    assert!(run("stop").success());
+    assert!(!run("status").success());
+    assert!(!user_pid_path.exists());
+    for _ in 0..50 {
+        if !alive() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    assert!(!alive());
+}
+
+#[rstest]
+fn service_ctl_clears_stale_pids_on_boot() {
+    let sandbox = tempfile::tempdir().unwrap();
+    let data_path = sandbox.path().join("data");
+    let bin_path = sandbox.path().join(BIN_DIR);
+    fs::create_dir_all(&bin_path).unwrap();
+    let daemon_path = bin_path.join("daemon");
+    fs::write(&daemon_path, "#!/bin/sh\nsleep 30\n").unwrap();
+    fs::set_permissions(&daemon_path, fs::Permissions::from_mode(0o755)).unwrap();
+    // A pid file left from the previous boot, pointing at a live process.
+    fs::create_dir_all(&data_path).unwrap();
+    let supervisor_pid_path = data_path.join("daemon.supervisor.pid");
+    fs::write(&supervisor_pid_path, format!("{}\n", std::process::id())).unwrap();
+
+    let mut scripts = ScriptSet::default();
+    scripts.push_services(&[service("daemon")], data_path.to_str().unwrap());
+    scripts.write(sandbox.path()).unwrap();
+    let post_fs_data = sandbox.path().join(ScriptKind::PostFsData.file_name());
+    assert!(
+        Command::new("sh")
+            .arg(&post_fs_data)
+            .status()
+            .unwrap()
+            .success()
+    );
+    assert!(!supervisor_pid_path.exists());
+
+    let ctl = sandbox.path().join(ScriptKind::ServiceCtl.file_name());
+    let run = |action: &str| Command::new("sh").arg(&ctl).arg(action).status().unwrap();
+    // With the stale pid file `start` would report "already running" and
+    // never launch the daemon.
+    assert!(run("start").success());
+    let pid_path = data_path.join("daemon.pid");
+    for _ in 0..50 {
+        if pid_path.exists() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    assert!(pid_path.exists());
+    assert!(run("stop").success());
+}
+
+#[rstest]
+fn bootloop_guard_disables_module() {
+    let sandbox = tempfile::tempdir().unwrap();
+    let module_prop = sandbox.path().join("module.prop");
+    fs::write(&module_prop, "id=a_module\ndescription=Original\n").unwrap();
+    let data_path = sandbox.path().join("data");
+    let mut scripts = ScriptSet::default();
+    scripts.push_bootloop_guard(2, data_path.to_str().unwrap(), Some("Original"));
+    scripts.write(sandbox.path()).unwrap();
+    let post_fs_data = sandbox.path().join(ScriptKind::PostFsData.file_name());
+    let run = || Command::new("sh").arg(&post_fs_data).status().unwrap();
+
+    assert!(run().success());
+    assert!(run().success());
+    assert_eq!(
+        fs::read_to_string(data_path.join("boot_count")).unwrap(),
+        "2\n"
+    );
+    assert!(!sandbox.path().join("disable").exists());
+
+    assert!(run().success());
+    assert!(sandbox.path().join("disable").exists());
+    assert!(!data_path.join("boot_count").exists());
+    assert_eq!(
+        fs::read_to_string(&module_prop).unwrap(),
+        "id=a_module\n\
+         description=[Disabled by bootloop guard after 2 unfinished boots] Original\n"
+    );
+}
+
+#[rstest]
+fn bootloop_guard_resets_on_boot_completed() {
+    let sandbox = tempfile::tempdir().unwrap();
+    let module_prop = sandbox.path().join("module.prop");
+    fs::write(&module_prop, "id=a_module\n").unwrap();
+    let fake_bin = sandbox.path().join("fake_bin");
+    fs::create_dir_all(&fake_bin).unwrap();
+    fs::write(fake_bin.join("getprop"), "#!/bin/sh\necho 1\n").unwrap();
+    fs::set_permissions(fake_bin.join("getprop"), fs::Permissions::from_mode(0o755)).unwrap();
+    let data_path = sandbox.path().join("data");
+    fs::create_dir_all(&data_path).unwrap();
+    fs::write(data_path.join("boot_count"), "1\n").unwrap();
+
+    let mut scripts = ScriptSet::default();
+    scripts.push_bootloop_guard(2, data_path.to_str().unwrap(), None);
+    scripts.write(sandbox.path()).unwrap();
+    let path = format!("{}:{}", fake_bin.display(), std::env::var("PATH").unwrap());
+    let status = Command::new("sh")
+        .arg(sandbox.path().join(ScriptKind::Service.file_name()))
+        .env("PATH", path)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    for _ in 0..50 {
+        if !data_path.join("boot_count").exists() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    assert!(!data_path.join("boot_count").exists());
+}
+
+#[rstest]
+#[case(Some("010200900"), "010300900\n")]
+#[case(Some("010000900"), "010200900\n010300900\n")]
+#[case(None, "")]
+fn data_dir_runs_migrations(#[case] installed: Option<&str>, #[case] expected: &str) {
+    let sandbox = tempfile::tempdir().unwrap();
+    let data_path = sandbox.path().join("data");
+    let installed_prop = sandbox.path().join("installed.prop");
+    if let Some(value) = installed {
+        fs::write(&installed_prop, format!("versionCode={}\n", value)).unwrap();
+    }
+    let source_path = sandbox.path().join("source");
+    fs::create_dir_all(&source_path).unwrap();
+    for version in ["1.2.0", "1.3.0"] {
+        let script = "echo \"${0##*/}\" | cut -d. -f1 >>\"$DATADIR/log\"\n";
+        fs::write(source_path.join(format!("{}.sh", version)), script).unwrap();
+    }
+    // Built directly, the sandbox is outside of /data/adb.
+    let mut data_dir = DataDir {
+        path: data_path.to_str().unwrap().to_string(),
+        clean_on_uninstall: true,
+        migrations: vec![],
+    };
+    let version_code = VersionCode::try_from("1.3.0").unwrap();
+    data_dir
+        .load_migrations(&source_path, &version_code)
+        .unwrap();
+    let migrations_path = sandbox.path().join(DataDir::MIGRATIONS);
+    fs::create_dir_all(&migrations_path).unwrap();
+    for migration in &data_dir.migrations {
+        let dest = migrations_path.join(format!("{}.sh", migration.version_code));
+        fs::copy(&migration.source, dest).unwrap();
+    }
+
+    let mut scripts = ScriptSet::default();
+    scripts.push_data_dir(&data_dir, installed_prop.to_str().unwrap());
+    scripts.write(sandbox.path()).unwrap();
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg("ui_print() { :; }; abort() { exit 1; }; MODPATH=$1; . \"$MODPATH/customize.sh\"")
+        .arg("sh")
+        .arg(sandbox.path())
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert_eq!(
+        fs::read_to_string(data_path.join("log")).unwrap_or_default(),
+        expected
+    );
+    assert!(!migrations_path.exists());
+
+    let uninstall = render(&scripts, ScriptKind::Uninstall, None);
+    assert!(uninstall.ends_with("rm -rf \"$DATADIR\"\n"));
+}
+
+#[rstest]
+#[case("sleep 2", true, false)]
+#[case("echo 'EV_KEY KEY_VOLUMEDOWN DOWN'", false, false)]
+#[case("echo 'EV_KEY KEY_VOLUMEUP DOWN'", true, true)]
+fn options_install_chosen_files(
+    #[case] getevent: &str,
+    #[case] extra_installed: bool,
+    #[case] alt_installed: bool,
+) {
+    let sandbox = tempfile::tempdir().unwrap();
+    let fake_bin = sandbox.path().join("fake_bin");
+    fs::create_dir_all(&fake_bin).unwrap();
+    fs::write(
+        fake_bin.join("getevent"),
+        format!("#!/bin/sh\n{}\n", getevent),
+    )
+    .unwrap();
+    fs::set_permissions(fake_bin.join("getevent"), fs::Permissions::from_mode(0o755)).unwrap();
+    let module_path = sandbox.path().join("module");
+    for name in ["extra", "alt"] {
+        let bin_path = module_path
+            .join(InstallOption::DIR)
+            .join(name)
+            .join("system/bin");
+        fs::create_dir_all(&bin_path).unwrap();
+        fs::write(bin_path.join(name), "").unwrap();
+    }
+
+    let mut scripts = ScriptSet::default();
+    scripts.push_options(&[
+        InstallOption::try_new("extra".to_string(), "Extra?".to_string(), true, 1).unwrap(),
+        InstallOption::try_new("alt".to_string(), "Alt?".to_string(), false, 1).unwrap(),
+    ]);
+    scripts.write(&module_path).unwrap();
+    let path = format!("{}:{}", fake_bin.display(), std::env::var("PATH").unwrap());
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg("ui_print() { :; }; MODPATH=$1; . \"$MODPATH/customize.sh\"")
+        .arg("sh")
+        .arg(&module_path)
+        .env("PATH", path)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert_eq!(
+        module_path.join("system/bin/extra").exists(),
+        extra_installed
+    );
+    assert_eq!(module_path.join("system/bin/alt").exists(), alt_installed);
+    assert!(!module_path.join(InstallOption::DIR).exists());
+}
+
+#[rstest]
+#[case("arm64-v8a,armeabi-v7a,armeabi", "30", "false", "27000", true)]
+#[case("arm64-v8a", "28", "false", "27000", false)]
+#[case("x86_64,x86", "30", "false", "27000", false)]
+#[case("arm64-v8a", "30", "false", "25000", false)]
+#[case("arm64-v8a", "30", "true", "", true)]
+fn requires_abort_installation(
+    #[case] abilist: &str,
+    #[case] api: &str,
+    #[case] ksu: &str,
+    #[case] magisk_ver_code: &str,
+    #[case] success: bool,
+) {
+    let sandbox = tempfile::tempdir().unwrap();
+    let fake_bin = sandbox.path().join("fake_bin");
+    fs::create_dir_all(&fake_bin).unwrap();
+    fs::write(
+        fake_bin.join("getprop"),
+        format!("#!/bin/sh\necho {}\n", abilist),
+    )
+    .unwrap();
+    fs::set_permissions(fake_bin.join("getprop"), fs::Permissions::from_mode(0o755)).unwrap();
+    let mut requires = Requires::new(&Target::Arm64V8a);
+    requires.min_api = Some(29);
+    requires.min_magisk = Some(26000);
+    requires.min_ksu = Some(11000);
+
+    let mut scripts = ScriptSet::default();
+    scripts.push_requires(&requires);
+    scripts.write(sandbox.path()).unwrap();
+    let path = format!("{}:{}", fake_bin.display(), std::env::var("PATH").unwrap());
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg("abort() { exit 1; }; MODPATH=$1; . \"$MODPATH/customize.sh\"")
+        .arg("sh")
+        .arg(sandbox.path())
+        .env("PATH", path)
+        .env("API", api)
+        .env("KSU", ksu)
+        .env("KSU_VER_CODE", "11500")
+        .env("MAGISK_VER_CODE", magisk_ver_code)
+        .status()
+        .unwrap();
+    assert_eq!(status.success(), success);
+}
+
+#[rstest]
+#[case(Target::ArmeabiV7a, "arm64-v8a,armeabi-v7a,armeabi", true)]
+#[case(Target::X86, "x86_64,x86", true)]
+#[case(Target::X86, "arm64-v8a,armeabi-v7a,armeabi", false)]
+#[case(Target::Arm64V8a, "armeabi-v7a,armeabi", false)]
+fn requires_default_abis(#[case] target: Target, #[case] abilist: &str, #[case] success: bool) {
+    let sandbox = tempfile::tempdir().unwrap();
+    let fake_bin = sandbox.path().join("fake_bin");
+    fs::create_dir_all(&fake_bin).unwrap();
+    let getprop = format!(
+        "#!/bin/sh\n[ \"$1\" = ro.product.cpu.abilist ] && echo {}\n",
+        abilist
+    );
+    fs::write(fake_bin.join("getprop"), getprop).unwrap();
+    fs::set_permissions(fake_bin.join("getprop"), fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut scripts = ScriptSet::default();
+    scripts.push_requires(&Requires::new(&target));
+    scripts.write(sandbox.path()).unwrap();
+    let path = format!("{}:{}", fake_bin.display(), std::env::var("PATH").unwrap());
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg("abort() { exit 1; }; MODPATH=$1; . \"$MODPATH/customize.sh\"")
+        .arg("sh")
+        .arg(sandbox.path())
+        .env("PATH", path)
+        .status()
+        .unwrap();
+    assert_eq!(status.success(), success);
+}
+
+#[rstest]
+fn requires_modules() {
+    let mut requires = Requires::new(&Target::X86);
+    requires.modules = vec!["zygisk_lsposed".to_string()];
+    requires.conflicts = vec!["other_module".to_string()];
+    let mut scripts = ScriptSet::default();
+    scripts.push_requires(&requires);
+
+    let customize = render(&scripts, ScriptKind::Customize, None);
+    assert!(customize.contains("        'x86') abi_supported=true ;;\n"));
+    assert!(customize.contains("if [ ! -d /data/adb/modules/zygisk_lsposed ] ||"));
+    assert!(customize.contains(
+        "if [ -d /data/adb/modules/other_module ] && [ ! -f /data/adb/modules/other_module/remove ]"
+    ));
+    assert!(!customize.contains("$API"));
+}
+
+#[rstest]
+#[case("true")]
+#[case("false")]
+fn overlays_remove_and_replace(#[case] ksu: &str) {
+    let sandbox = tempfile::tempdir().unwrap();
+    let device_dir = sandbox.path().join("device/app/Bloat");
+    fs::create_dir_all(&device_dir).unwrap();
+    let device_dir = device_dir.to_str().unwrap().to_string();
+    let device_file = sandbox.path().join("device/etc/hosts");
+    let device_file = device_file.to_str().unwrap().to_string();
+    let module_path = sandbox.path().join("module");
+    fs::create_dir_all(&module_path).unwrap();
+
+    let mut scripts = ScriptSet::default();
+    scripts.push_overlays(
+        &[device_dir.clone(), device_file.clone()],
+        &["/system/app/Foo".to_string()],
+    );
+    scripts.write(&module_path).unwrap();
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg("MODPATH=$1; . \"$MODPATH/customize.sh\"; echo \"$REPLACE\"; echo \"$REMOVE\"")
+        .arg("sh")
+        .arg(&module_path)
+        .env("KSU", ksu)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let module_dir = module_path.join(device_dir.trim_start_matches('/'));
+    let module_file = module_path.join(device_file.trim_start_matches('/'));
+
+    if ksu == "true" {
+        let expected = format!("/system/app/Foo\n{} {}\n", device_dir, device_file);
+        assert_eq!(stdout, expected);
+        assert!(!module_dir.exists());
+        assert!(!module_file.exists());
+    } else {
+        assert_eq!(stdout, "/system/app/Foo\n\n");
+        assert!(module_dir.join(".replace").is_file());
+        assert!(module_file.is_file());
+    }
+}
+
+fn render(scripts: &ScriptSet, kind: ScriptKind, user: Option<&str>) -> String {
+    ScriptSet::render(kind, &scripts.scripts[&kind], user)
+}
+
+fn entry(binary: &str, stage: Stage, wait_for_boot: bool, log: Option<&str>) -> ScriptEntry {
+    ScriptEntry {
+        binary: binary.to_string(),
+        args: vec!["--flag".to_string()],
+        stage,
+        wait_for_boot,
+        log: log.map(str::to_string),
+    }
+}
+
+fn service(name: &str) -> Service {
+    Service {
+        name: name.to_string(),
+        binary: name.to_string(),
+        args: vec!["--flag".to_string()],
+        user: None,
+        restart: RestartPolicy::OnFailure,
+        backoff: 1,
+        max_backoff: 60,
+        log: None,
+    }
+}
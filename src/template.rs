@@ -0,0 +1,43 @@
+#[cfg(test)]
+mod test;
+
+use anyhow::{Result, bail};
+use regex::Regex;
+use std::collections::BTreeMap;
+
+/// Variables every template gets from the module and the current build.
+pub const BUILTIN: [&str; 4] = ["id", "version", "version_code", "target"];
+
+/// Replaces `{{ name }}` with values from `vars`. A name missing from `vars`
+/// is an error.
+pub fn render(text: &str, vars: &BTreeMap<String, String>) -> Result<String> {
+    let re = Regex::new(r"\{\{\s*([^{}]*?)\s*\}\}")?;
+    let mut result = String::with_capacity(text.len());
+    let mut last = 0;
+    for caps in re.captures_iter(text) {
+        let Some(all) = caps.get(0) else {
+            continue;
+        };
+        let name = &caps[1];
+        let Some(value) = vars.get(name) else {
+            bail!("Undefined template variable: '{}'", name);
+        };
+        result.push_str(&text[last..all.start()]);
+        result.push_str(value);
+        last = all.end();
+    }
+    result.push_str(&text[last..]);
+    Ok(result)
+}
+
+/// Checks the name of a custom variable from `[package.metadata.magisk.vars]`.
+pub fn check_name(name: &str) -> Result<()> {
+    let re = Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_]*$")?;
+    if !re.is_match(name) {
+        bail!("Invalid var name: '{}'", name);
+    }
+    if BUILTIN.contains(&name) {
+        bail!("Invalid var name: '{}' is built in", name);
+    }
+    Ok(())
+}
@@ -0,0 +1,44 @@
+use super::*;
+use rstest::rstest;
+
+#[rstest]
+#[case("id={{id}}", "id=a_module")]
+#[case("{{ id }}-{{  version  }}", "a_module-1.2.3")]
+#[case("no vars { here }", "no vars { here }")]
+#[case("${{ greeting }}", "$hello")]
+fn render_ok(#[case] text: &str, #[case] expected: &str) {
+    assert_eq!(render(text, &vars()).unwrap(), expected);
+}
+
+#[rstest]
+fn render_err(#[values("{{ missing }}", "{{}}", "{{ id.x }}")] text: &str) {
+    let result = render(text, &vars());
+    assert!(
+        result.is_err(),
+        "result.is_err() not true. text: '{}'",
+        text
+    );
+}
+
+#[rstest]
+fn check_name_ok(#[values("greeting", "_x", "A1")] name: &str) {
+    assert!(check_name(name).is_ok());
+}
+
+#[rstest]
+fn check_name_err(#[values("", "1a", "a-b", "id", "version_code")] name: &str) {
+    let result = check_name(name);
+    assert!(
+        result.is_err(),
+        "result.is_err() not true. name: '{}'",
+        name
+    );
+}
+
+fn vars() -> BTreeMap<String, String> {
+    BTreeMap::from([
+        ("id".to_string(), "a_module".to_string()),
+        ("version".to_string(), "1.2.3".to_string()),
+        ("greeting".to_string(), "hello".to_string()),
+    ])
+}